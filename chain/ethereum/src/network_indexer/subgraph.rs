@@ -93,6 +93,8 @@ fn create_subgraph(
         data_sources: vec![],
         graft: None,
         templates: vec![],
+        features: vec![],
+        indexer_hints: None,
     };
 
     // Create deployment entity
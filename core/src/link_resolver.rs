@@ -12,6 +12,9 @@ use lazy_static::lazy_static;
 use lru_time_cache::LruCache;
 use serde_json::Value;
 
+use graph::components::link_resolver::{
+    link_timeout_error, with_link_timeout, DEFAULT_RETRY_BACKOFF,
+};
 use graph::prelude::{LinkResolver as LinkResolverTrait, *};
 
 /// Environment variable for limiting the `ipfs.map` file size limit.
@@ -64,22 +67,23 @@ fn read_u64_from_env(name: &str) -> Option<u64> {
 async fn select_fastest_client_with_stat<'a>(
     clients: &'a [IpfsClient],
     logger: &'a Logger,
-    path: &'_ str,
+    link: &'_ Link,
     timeout: Duration,
-    do_retry: bool,
+    max_attempts: u32,
+    base_backoff: Duration,
 ) -> Result<(ObjectStatResponse, &'a IpfsClient), failure::Error> {
+    // Discard the `/ipfs/` prefix (if present) to get the hash.
+    let path = link.link.trim_start_matches("/ipfs/");
     let mut err: Option<failure::Error> = None;
 
     let mut stats: FuturesUnordered<_> = clients
         .iter()
         .enumerate()
         .map(|(i, c)| {
-            let retry_fut = if do_retry {
-                retry("object.stat", logger).no_limit()
-            } else {
-                retry("object.stat", logger).limit(1)
-            }
-            .timeout(timeout);
+            let retry_fut = retry("object.stat", logger)
+                .limit(max_attempts as usize)
+                .backoff(base_backoff)
+                .timeout(timeout);
 
             retry_fut
                 .run(move || c.object_stat(path).map_ok(move |s| (s, i)).boxed().compat())
@@ -92,7 +96,7 @@ async fn select_fastest_client_with_stat<'a>(
             Ok((stat, index)) => {
                 return Ok((stat, &clients[index]));
             }
-            Err(e) => err = Some(e.into()),
+            Err(e) => err = Some(link_timeout_error(e, &link.link, timeout)),
         }
     }
 
@@ -128,7 +132,24 @@ pub struct LinkResolver {
     clients: Arc<Vec<IpfsClient>>,
     cache: Arc<Mutex<LruCache<String, Vec<u8>>>>,
     timeout: Duration,
-    retry: bool,
+    max_attempts: u32,
+    base_backoff: Duration,
+    max_file_size: Option<usize>,
+}
+
+impl LinkResolver {
+    // Returns an error if `size` exceeds the limit set with `with_max_file_size`
+    fn check_max_file_size(&self, link: &Link, size: u64) -> Result<(), failure::Error> {
+        match self.max_file_size {
+            Some(max_file_size) if size > max_file_size as u64 => Err(FileSizeLimitExceeded {
+                link: link.link.clone(),
+                max_file_size,
+                size,
+            }
+            .into()),
+            _ => Ok(()),
+        }
+    }
 }
 
 impl From<IpfsClient> for LinkResolver {
@@ -145,7 +166,9 @@ impl From<Vec<IpfsClient>> for LinkResolver {
                 *MAX_IPFS_CACHE_SIZE as usize,
             ))),
             timeout: *IPFS_TIMEOUT,
-            retry: false,
+            max_attempts: 1,
+            base_backoff: DEFAULT_RETRY_BACKOFF,
+            max_file_size: None,
         }
     }
 }
@@ -157,8 +180,14 @@ impl LinkResolverTrait for LinkResolver {
         self
     }
 
-    fn with_retries(mut self) -> Self {
-        self.retry = true;
+    fn with_retries_config(mut self, max_attempts: u32, base_backoff: Duration) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self.base_backoff = base_backoff;
+        self
+    }
+
+    fn with_max_file_size(mut self, bytes: usize) -> Self {
+        self.max_file_size = Some(bytes);
         self
     }
 
@@ -169,26 +198,32 @@ impl LinkResolverTrait for LinkResolver {
 
         if let Some(data) = self.cache.lock().unwrap().get(&path) {
             trace!(logger, "IPFS cache hit"; "hash" => &path);
+            self.check_max_file_size(link, data.len() as u64)?;
             return Ok(data.clone());
         }
         trace!(logger, "IPFS cache miss"; "hash" => &path);
 
-        let (stat, client) =
-            select_fastest_client_with_stat(&self.clients, logger, &path, self.timeout, self.retry)
-                .await?;
+        let (stat, client) = select_fastest_client_with_stat(
+            &self.clients,
+            logger,
+            link,
+            self.timeout,
+            self.max_attempts,
+            self.base_backoff,
+        )
+        .await?;
 
         // FIXME: Having an env variable here is a problem for consensus.
         // Index Nodes should not disagree on whether the file should be read.
         let max_file_size: Option<u64> = read_u64_from_env(MAX_IPFS_FILE_SIZE_VAR);
         restrict_file_size(&path, &stat, &max_file_size)?;
+        self.check_max_file_size(link, stat.cumulative_size)?;
 
         let path = path.clone();
-        let retry_fut = if self.retry {
-            retry("ipfs.cat", &logger).no_limit()
-        } else {
-            retry("ipfs.cat", &logger).limit(1)
-        }
-        .timeout(self.timeout);
+        let retry_fut = retry("ipfs.cat", &logger)
+            .limit(self.max_attempts as usize)
+            .backoff(self.base_backoff)
+            .timeout(self.timeout);
 
         let data = retry_fut
             .run(move || {
@@ -214,7 +249,8 @@ impl LinkResolverTrait for LinkResolver {
                 .compat()
             })
             .compat()
-            .await?;
+            .await
+            .map_err(|e| link_timeout_error(e, &link.link, self.timeout))?;
 
         Ok(data)
     }
@@ -223,15 +259,34 @@ impl LinkResolverTrait for LinkResolver {
         // Discard the `/ipfs/` prefix (if present) to get the hash.
         let path = link.link.trim_start_matches("/ipfs/");
 
-        let (stat, client) =
-            select_fastest_client_with_stat(&self.clients, logger, path, self.timeout, self.retry)
-                .await?;
+        let (stat, client) = select_fastest_client_with_stat(
+            &self.clients,
+            logger,
+            link,
+            self.timeout,
+            self.max_attempts,
+            self.base_backoff,
+        )
+        .await?;
 
         let max_file_size =
             read_u64_from_env(MAX_IPFS_MAP_FILE_SIZE_VAR).or(Some(DEFAULT_MAX_IPFS_MAP_FILE_SIZE));
         restrict_file_size(path, &stat, &max_file_size)?;
 
-        let mut stream = client.cat(&path).compat().fuse();
+        // Each chunk has to arrive within the timeout, since the file may be
+        // too large to download in its entirety within it
+        let chunks = futures03::stream::unfold(
+            (client.cat(&path), link.clone(), self.timeout),
+            |(mut chunks, link, timeout)| async move {
+                let chunk = with_link_timeout(&link, timeout, async {
+                    Ok::<_, Error>(chunks.next().await.transpose()?)
+                })
+                .await
+                .transpose()?;
+                Some((chunk, (chunks, link, timeout)))
+            },
+        );
+        let mut stream = Box::pin(chunks).compat().fuse();
 
         let mut buf = BytesMut::with_capacity(1024);
 
@@ -241,11 +296,16 @@ impl LinkResolverTrait for LinkResolver {
         // to the line number in the overall file
         let mut count = 0;
 
+        // The number of bytes in the lines that were taken out of `buf`
+        let mut consumed = 0;
+
         let stream: JsonValueStream = Box::pin(
             poll_fn(move || -> Poll<Option<JsonStreamValue>, failure::Error> {
                 loop {
                     if let Some(offset) = buf.iter().position(|b| *b == b'\n') {
                         let line_bytes = buf.split_to(offset + 1);
+                        let byte_offset = consumed;
+                        consumed += line_bytes.len();
                         count += 1;
                         if line_bytes.len() > 1 {
                             let line = std::str::from_utf8(&line_bytes)?;
@@ -253,6 +313,7 @@ impl LinkResolverTrait for LinkResolver {
                                 Ok(v) => Ok(Async::Ready(Some(JsonStreamValue {
                                     value: v,
                                     line: count,
+                                    byte_offset,
                                 }))),
                                 Err(e) => {
                                     // Adjust the line number in the serde error. This
@@ -309,7 +309,7 @@ where
         .await?;
 
         let (manifest, validation_warnings) = unvalidated
-            .validate(self.store.clone())
+            .validate(self.store.clone(), &self.chain_stores)
             .map_err(SubgraphRegistrarError::ManifestValidationError)?;
 
         let network_name = manifest.network_name();
@@ -490,12 +490,8 @@ fn resolve_subgraph_chain_blocks(
         // If the minimum start block is 0 (i.e. the genesis block),
         // return `None` to start indexing from the genesis block. Otherwise
         // return a block pointer for the block with number `min_start_block - 1`.
-        match manifest
-            .start_blocks()
-            .into_iter()
-            .min()
-            .expect("cannot identify minimum start block because there are no data sources")
-        {
+        // Subgraphs with only templates start at the genesis block.
+        match manifest.start_blocks().into_iter().min().unwrap_or(0) {
             0 => Box::new(future::ok(None)) as Box<dyn Future<Item = _, Error = _> + Send>,
             min_start_block => Box::new(
                 ethereum_adapter
@@ -522,21 +518,28 @@ fn resolve_subgraph_chain_blocks(
                 Some(base) => {
                     let base_block = base.block;
                     Box::new(
-                        ethereum_adapter
-                            .block_pointer_from_number(
-                                &logger1,
-                                chain_store1.clone(),
-                                base.block as u64,
-                            )
-                            .map(|ptr| Some((base.base, ptr)))
-                            .map_err(move |_| {
-                                SubgraphRegistrarError::ManifestValidationError(vec![
-                                    SubgraphManifestValidationError::BlockNotFound(format!(
-                                        "graft base block {} not found",
-                                        base_block
-                                    )),
-                                ])
+                        future::result(
+                            base.base_id().map_err(|e| {
+                                SubgraphRegistrarError::ManifestValidationError(vec![e])
                             }),
+                        )
+                        .and_then(move |base_id| {
+                            ethereum_adapter
+                                .block_pointer_from_number(
+                                    &logger1,
+                                    chain_store1.clone(),
+                                    base_block as u64,
+                                )
+                                .map(|ptr| Some((base_id, ptr)))
+                                .map_err(move |_| {
+                                    SubgraphRegistrarError::ManifestValidationError(vec![
+                                        SubgraphManifestValidationError::BlockNotFound(format!(
+                                            "graft base block {} not found",
+                                            base_block
+                                        )),
+                                    ])
+                                })
+                        }),
                     ) as Box<dyn Future<Item = _, Error = _> + Send>
                 }
             }
@@ -1,44 +1,799 @@
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::fs;
+use std::future::Future;
+use std::path::{Component, Path, PathBuf};
 use std::pin::Pin;
+use std::sync::Mutex;
 use std::time::Duration;
 
 use async_trait::async_trait;
-use failure::Error;
+use failure::{format_err, Error, Fail};
+use futures03::compat::Future01CompatExt;
+use futures03::future::{FutureExt, TryFutureExt};
 use futures03::prelude::Stream;
+use futures03::stream::{self, StreamExt, TryStreamExt};
+use lru_time_cache::LruCache;
 use serde_json::Value;
-use slog::Logger;
+use sha2::{Digest, Sha256};
+use slog::{debug, Logger};
 
 use crate::data::subgraph::Link;
+use crate::prelude::{Deserialize, Serialize};
+use crate::util::futures::{retry, TimeoutError};
 
 /// The values that `json_stream` returns. The struct contains the deserialized
 /// JSON value from the input stream, together with the line number from which
-/// the value was read.
+/// the value was read and the offset of the start of that line in bytes.
 pub struct JsonStreamValue {
     pub value: Value,
     pub line: usize,
+    pub byte_offset: usize,
 }
 
 pub type JsonValueStream =
     Pin<Box<dyn Stream<Item = Result<JsonStreamValue, Error>> + Send + 'static>>;
 
+/// Deserialize the newline-delimited JSON values in `bytes` into a
+/// `JsonValueStream`. Empty lines are skipped, but still count towards the
+/// line numbers of the values that follow them.
+pub fn json_value_stream(bytes: Vec<u8>) -> Result<JsonValueStream, Error> {
+    let text = String::from_utf8(bytes)?;
+    let mut next_offset = 0;
+    let values: Vec<_> = text
+        .split_terminator('\n')
+        .map(|line| {
+            let byte_offset = next_offset;
+            next_offset += line.len() + 1;
+            // Lines may end in `\r\n`, like they can for `str::lines`
+            if line.ends_with('\r') {
+                (byte_offset, &line[..line.len() - 1])
+            } else {
+                (byte_offset, line)
+            }
+        })
+        .enumerate()
+        .filter(|(_, (_, line))| !line.is_empty())
+        .map(
+            |(index, (byte_offset, line))| match serde_json::from_str::<Value>(line) {
+                Ok(value) => Ok(JsonStreamValue {
+                    value,
+                    line: index + 1,
+                    byte_offset,
+                }),
+                Err(e) => {
+                    // Report the line number in the overall text rather than the
+                    // one serde_json reports for the single line it saw
+                    let msg = e.to_string();
+                    let msg = msg.split(" at line ").next().unwrap();
+                    Err(format_err!(
+                        "{} at line {} column {}: '{}'",
+                        msg,
+                        index + 1,
+                        e.column(),
+                        line
+                    ))
+                }
+            },
+        )
+        .collect();
+    Ok(Box::pin(stream::iter(values)))
+}
+
+/// Returned by `LinkResolver::cat` when a file is larger than the limit set
+/// with `LinkResolver::with_max_file_size`.
+#[derive(Fail, Debug, PartialEq)]
+#[fail(
+    display = "file {} is too large. It can be at most {} bytes but is {} bytes",
+    link, max_file_size, size
+)]
+pub struct FileSizeLimitExceeded {
+    pub link: String,
+    pub max_file_size: usize,
+    pub size: u64,
+}
+
+/// Returned by `LinkResolver` implementations when fetching a link takes
+/// longer than the timeout set with `LinkResolver::with_timeout`.
+#[derive(Fail, Debug, PartialEq)]
+#[fail(display = "fetching {} timed out after {:?}", link, timeout)]
+pub struct LinkTimeout {
+    pub link: String,
+    pub timeout: Duration,
+}
+
+/// Turns the error of a request for `link` that was made with `timeout`
+/// into a `LinkTimeout` if the request timed out, and into the request's own
+/// error otherwise.
+pub fn link_timeout_error<E>(e: TimeoutError<E>, link: &str, timeout: Duration) -> Error
+where
+    E: Into<Error> + Debug + Send + Sync + 'static,
+{
+    match e {
+        TimeoutError::Elapsed => LinkTimeout {
+            link: link.to_owned(),
+            timeout,
+        }
+        .into(),
+        TimeoutError::Inner(e) => e.into(),
+    }
+}
+
+/// Runs `fut`, but fails with a `LinkTimeout` error if it does not finish
+/// within `timeout`. The future is dropped, and with it any request that is
+/// still in flight.
+pub async fn with_link_timeout<T>(
+    link: &Link,
+    timeout: Duration,
+    fut: impl Future<Output = Result<T, Error>>,
+) -> Result<T, Error> {
+    let result = match tokio::time::timeout(timeout, fut).await {
+        Ok(result) => result.map_err(TimeoutError::Inner),
+        Err(_) => Err(TimeoutError::Elapsed),
+    };
+    result.map_err(|e| link_timeout_error(e, &link.link, timeout))
+}
+
+/// Returned by `VerifyingLinkResolver::cat` when the data for an IPFS link
+/// does not have the hash that the link names.
+#[derive(Fail, Debug, PartialEq)]
+#[fail(
+    display = "the content of {} does not match its hash, it has hash {}",
+    link, actual
+)]
+pub struct ContentHashMismatch {
+    pub link: String,
+    pub actual: String,
+}
+
+/// The maximum number of `cat` requests that `cat_many` has in flight at once.
+const CAT_MANY_CONCURRENCY: usize = 8;
+
+/// How long `with_retries` waits before the first retry.
+pub const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_millis(2);
+
 /// Resolves links to subgraph manifests and resources referenced by them.
 #[async_trait]
 pub trait LinkResolver: Send + Sync + 'static {
-    /// Updates the timeout used by the resolver.
+    /// Updates the timeout used by the resolver. Requests that take longer
+    /// than that are cancelled and fail with a `LinkTimeout` error.
     fn with_timeout(self, timeout: Duration) -> Self
     where
         Self: Sized;
 
-    /// Enables infinite retries.
+    /// Enables infinite retries, with `DEFAULT_RETRY_BACKOFF` as the initial
+    /// backoff.
     fn with_retries(self) -> Self
+    where
+        Self: Sized,
+    {
+        self.with_retries_config(std::u32::MAX, DEFAULT_RETRY_BACKOFF)
+    }
+
+    /// Makes at most `max_attempts` attempts for each request, waiting
+    /// `base_backoff` before the first retry and twice as long before each
+    /// further retry. At least one attempt is always made.
+    fn with_retries_config(self, max_attempts: u32, base_backoff: Duration) -> Self
+    where
+        Self: Sized;
+
+    /// Limits the size of files returned by `cat` to `bytes`. Fetching a
+    /// larger file fails with a `FileSizeLimitExceeded` error.
+    fn with_max_file_size(self, bytes: usize) -> Self
     where
         Self: Sized;
 
     /// Fetches the link contents as bytes.
     async fn cat(&self, logger: &Logger, link: &Link) -> Result<Vec<u8>, Error>;
 
+    /// Fetches the contents of all `links` as bytes, returning them in the
+    /// same order as `links`. At most a few links are fetched concurrently;
+    /// the first error aborts the whole operation.
+    async fn cat_many(&self, logger: &Logger, links: &[Link]) -> Result<Vec<Vec<u8>>, Error> {
+        stream::iter(links)
+            .map(|link| self.cat(logger, link))
+            .buffered(CAT_MANY_CONCURRENCY)
+            .try_collect()
+            .await
+    }
+
     /// Read the contents of `link` and deserialize them into a stream of JSON
     /// values. The values must each be on a single line; newlines are significant
     /// as they are used to split the file contents and each line is deserialized
     /// separately.
-    async fn json_stream(&self, logger: &Logger, link: &Link) -> Result<JsonValueStream, Error>;
+    ///
+    /// The default implementation reads the whole file with `cat` and then
+    /// deserializes it with `json_value_stream`.
+    async fn json_stream(&self, logger: &Logger, link: &Link) -> Result<JsonValueStream, Error> {
+        json_value_stream(self.cat(logger, link).await?)
+    }
+}
+
+/// A `LinkResolver` that wraps another resolver and keeps the results of the
+/// most recent `cat` calls in an LRU cache, so that fetching the same link
+/// repeatedly only hits the wrapped resolver once. Concurrent requests for a
+/// link that is not cached yet may all go to the wrapped resolver.
+pub struct CachingLinkResolver<R> {
+    inner: R,
+    cache: Mutex<LruCache<String, Vec<u8>>>,
+    max_file_size: Option<usize>,
+}
+
+impl<R: LinkResolver> CachingLinkResolver<R> {
+    /// Wrap `inner` with a cache that holds at most `capacity` files.
+    pub fn new(inner: R, capacity: usize) -> Self {
+        Self {
+            inner,
+            cache: Mutex::new(LruCache::with_capacity(capacity)),
+            max_file_size: None,
+        }
+    }
+}
+
+#[async_trait]
+impl<R: LinkResolver> LinkResolver for CachingLinkResolver<R> {
+    fn with_timeout(self, timeout: Duration) -> Self {
+        Self {
+            inner: self.inner.with_timeout(timeout),
+            cache: self.cache,
+            max_file_size: self.max_file_size,
+        }
+    }
+
+    fn with_retries_config(self, max_attempts: u32, base_backoff: Duration) -> Self {
+        Self {
+            inner: self.inner.with_retries_config(max_attempts, base_backoff),
+            cache: self.cache,
+            max_file_size: self.max_file_size,
+        }
+    }
+
+    fn with_max_file_size(self, bytes: usize) -> Self {
+        Self {
+            inner: self.inner.with_max_file_size(bytes),
+            cache: self.cache,
+            max_file_size: Some(bytes),
+        }
+    }
+
+    async fn cat(&self, logger: &Logger, link: &Link) -> Result<Vec<u8>, Error> {
+        if let Some(data) = self.cache.lock().unwrap().get(&link.link) {
+            // The file may have been cached before the limit was lowered
+            return match self.max_file_size {
+                Some(max_file_size) if data.len() > max_file_size => Err(FileSizeLimitExceeded {
+                    link: link.link.clone(),
+                    max_file_size,
+                    size: data.len() as u64,
+                }
+                .into()),
+                _ => Ok(data.clone()),
+            };
+        }
+
+        let data = self.inner.cat(logger, link).await?;
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(link.link.clone(), data.clone());
+        Ok(data)
+    }
+
+    async fn json_stream(&self, logger: &Logger, link: &Link) -> Result<JsonValueStream, Error> {
+        self.inner.json_stream(logger, link).await
+    }
+}
+
+/// The size of the chunks into which `ipfs add` splits files by default.
+/// Files that fit into one chunk are stored as a single block.
+const IPFS_CHUNK_SIZE: usize = 262_144;
+
+/// A `LinkResolver` that wraps another resolver and checks that the data
+/// `cat` returns for an `/ipfs/` link has the hash the link names. This
+/// protects against IPFS nodes or gateways that serve the wrong data.
+///
+/// Only files that `ipfs add` stores as a single block with its default
+/// settings, i.e., files of at most 256KiB behind CIDv0 links like
+/// `/ipfs/Qm...`, can be verified; verifying larger files would require
+/// knowing how they were split into blocks. Data for all other links is
+/// passed through unchanged. Since `json_stream` reads the whole file with
+/// `cat`, it does not stream from the wrapped resolver.
+pub struct VerifyingLinkResolver<R> {
+    inner: R,
+}
+
+impl<R: LinkResolver> VerifyingLinkResolver<R> {
+    /// Verify the data returned by `inner`.
+    pub fn new(inner: R) -> Self {
+        Self { inner }
+    }
+}
+
+/// The multihash of the sha256 digest in a CIDv0 link like `/ipfs/Qm...`,
+/// or `None` if `link` is not such a link.
+fn cid_v0_multihash(link: &str) -> Option<Vec<u8>> {
+    let cid = link.trim_start_matches("/ipfs/");
+    if cid.len() != 46 || !cid.starts_with("Qm") {
+        return None;
+    }
+    bs58::decode(cid)
+        .into_vec()
+        .ok()
+        .filter(|hash| hash.len() == 34 && hash[0..2] == [0x12, 0x20])
+}
+
+fn write_protobuf_varint(mut n: u64, out: &mut Vec<u8>) {
+    while n >= 0x80 {
+        out.push((n as u8 & 0x7f) | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+/// The multihash of the block in which `ipfs add` stores `data` when it
+/// fits into a single chunk: a dag-pb node without links whose data is a
+/// UnixFS `File` message with `data` as its content.
+fn ipfs_single_block_multihash(data: &[u8]) -> Vec<u8> {
+    let mut unixfs = vec![0x08, 0x02];
+    if !data.is_empty() {
+        unixfs.push(0x12);
+        write_protobuf_varint(data.len() as u64, &mut unixfs);
+        unixfs.extend_from_slice(data);
+    }
+    unixfs.push(0x18);
+    write_protobuf_varint(data.len() as u64, &mut unixfs);
+
+    let mut node = vec![0x0a];
+    write_protobuf_varint(unixfs.len() as u64, &mut node);
+    node.extend_from_slice(&unixfs);
+
+    let mut multihash = vec![0x12, 0x20];
+    multihash.extend_from_slice(&Sha256::digest(&node));
+    multihash
+}
+
+#[async_trait]
+impl<R: LinkResolver> LinkResolver for VerifyingLinkResolver<R> {
+    fn with_timeout(self, timeout: Duration) -> Self {
+        Self {
+            inner: self.inner.with_timeout(timeout),
+        }
+    }
+
+    fn with_retries_config(self, max_attempts: u32, base_backoff: Duration) -> Self {
+        Self {
+            inner: self.inner.with_retries_config(max_attempts, base_backoff),
+        }
+    }
+
+    fn with_max_file_size(self, bytes: usize) -> Self {
+        Self {
+            inner: self.inner.with_max_file_size(bytes),
+        }
+    }
+
+    async fn cat(&self, logger: &Logger, link: &Link) -> Result<Vec<u8>, Error> {
+        let data = self.inner.cat(logger, link).await?;
+
+        let expected = match cid_v0_multihash(&link.link) {
+            Some(expected) => expected,
+            None => return Ok(data),
+        };
+        if data.len() > IPFS_CHUNK_SIZE {
+            debug!(logger, "Not verifying the hash of a file that spans several blocks";
+                   "link" => &link.link, "size" => data.len());
+            return Ok(data);
+        }
+
+        let actual = ipfs_single_block_multihash(&data);
+        if actual != expected {
+            return Err(ContentHashMismatch {
+                link: link.link.clone(),
+                actual: bs58::encode(actual).into_string(),
+            }
+            .into());
+        }
+        Ok(data)
+    }
+}
+
+/// The responses that a `RecordingLinkResolver` captured, keyed by link. A
+/// recording can be saved to a JSON file and loaded again to replay it with
+/// a `ReplayLinkResolver`, which makes tests that resolve links independent
+/// of an IPFS node.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LinkRecording {
+    /// The results of `cat`, hex-encoded
+    cats: BTreeMap<String, String>,
+    /// The values of `json_stream`, with their line numbers and byte offsets
+    json_streams: BTreeMap<String, Vec<(usize, usize, Value)>>,
+}
+
+impl LinkRecording {
+    /// Read a recording from the JSON file at `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|e| format_err!("failed to read {}: {}", path.display(), e))?;
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    /// Write the recording to the JSON file at `path`, replacing the file if
+    /// it exists.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        let text = serde_json::to_string_pretty(self)?;
+        fs::write(path, text).map_err(|e| format_err!("failed to write {}: {}", path.display(), e))
+    }
+}
+
+/// A `LinkResolver` that wraps another resolver and records the results of
+/// all successful `cat` and `json_stream` calls in a `LinkRecording`. A
+/// `json_stream` is only recorded if all of its values could be read.
+pub struct RecordingLinkResolver<R> {
+    inner: R,
+    recording: Mutex<LinkRecording>,
+}
+
+impl<R: LinkResolver> RecordingLinkResolver<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            recording: Mutex::new(LinkRecording::default()),
+        }
+    }
+
+    /// Everything that has been recorded so far
+    pub fn recording(&self) -> LinkRecording {
+        self.recording.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl<R: LinkResolver> LinkResolver for RecordingLinkResolver<R> {
+    fn with_timeout(self, timeout: Duration) -> Self {
+        Self {
+            inner: self.inner.with_timeout(timeout),
+            recording: self.recording,
+        }
+    }
+
+    fn with_retries_config(self, max_attempts: u32, base_backoff: Duration) -> Self {
+        Self {
+            inner: self.inner.with_retries_config(max_attempts, base_backoff),
+            recording: self.recording,
+        }
+    }
+
+    fn with_max_file_size(self, bytes: usize) -> Self {
+        Self {
+            inner: self.inner.with_max_file_size(bytes),
+            recording: self.recording,
+        }
+    }
+
+    async fn cat(&self, logger: &Logger, link: &Link) -> Result<Vec<u8>, Error> {
+        let data = self.inner.cat(logger, link).await?;
+        self.recording
+            .lock()
+            .unwrap()
+            .cats
+            .insert(link.link.clone(), hex::encode(&data));
+        Ok(data)
+    }
+
+    async fn json_stream(&self, logger: &Logger, link: &Link) -> Result<JsonValueStream, Error> {
+        let values: Vec<JsonStreamValue> = self
+            .inner
+            .json_stream(logger, link)
+            .await?
+            .try_collect()
+            .await?;
+        let recorded = values
+            .iter()
+            .map(|value| (value.line, value.byte_offset, value.value.clone()))
+            .collect();
+        self.recording
+            .lock()
+            .unwrap()
+            .json_streams
+            .insert(link.link.clone(), recorded);
+        Ok(Box::pin(stream::iter(values.into_iter().map(Ok))))
+    }
+}
+
+/// A `LinkResolver` that serves the responses in a `LinkRecording` and
+/// fails for links that were not recorded. Timeouts, retries and file size
+/// limits do not apply to it.
+pub struct ReplayLinkResolver {
+    recording: LinkRecording,
+}
+
+impl ReplayLinkResolver {
+    pub fn new(recording: LinkRecording) -> Self {
+        Self { recording }
+    }
+
+    /// Replay the recording in the JSON file at `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        LinkRecording::load(path).map(Self::new)
+    }
+}
+
+#[async_trait]
+impl LinkResolver for ReplayLinkResolver {
+    fn with_timeout(self, _timeout: Duration) -> Self {
+        self
+    }
+
+    fn with_retries_config(self, _max_attempts: u32, _base_backoff: Duration) -> Self {
+        self
+    }
+
+    fn with_max_file_size(self, _bytes: usize) -> Self {
+        self
+    }
+
+    async fn cat(&self, _logger: &Logger, link: &Link) -> Result<Vec<u8>, Error> {
+        let data = self
+            .recording
+            .cats
+            .get(&link.link)
+            .ok_or_else(|| format_err!("no recorded content for {}", link.link))?;
+        Ok(hex::decode(data)?)
+    }
+
+    async fn json_stream(&self, _logger: &Logger, link: &Link) -> Result<JsonValueStream, Error> {
+        let values = self
+            .recording
+            .json_streams
+            .get(&link.link)
+            .ok_or_else(|| format_err!("no recorded JSON stream for {}", link.link))?
+            .iter()
+            .map(|(line, byte_offset, value)| {
+                Ok(JsonStreamValue {
+                    value: value.clone(),
+                    line: *line,
+                    byte_offset: *byte_offset,
+                })
+            })
+            .collect::<Vec<_>>();
+        Ok(Box::pin(stream::iter(values)))
+    }
+}
+
+/// A `LinkResolver` that reads files from a local directory, which is handy
+/// when developing subgraphs without an IPFS node. Links are paths relative
+/// to the base directory and may be prefixed with `file:`. Links that point
+/// outside of the base directory are rejected.
+pub struct FileLinkResolver {
+    base_dir: PathBuf,
+    max_file_size: Option<usize>,
+}
+
+impl FileLinkResolver {
+    /// Resolve links to files in `base_dir`.
+    pub fn new(base_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            base_dir: base_dir.into(),
+            max_file_size: None,
+        }
+    }
+
+    // Resolves `link` to the path of an existing file in `base_dir`. This
+    // reads the file system and therefore blocks
+    fn path(base_dir: &Path, link: &Link) -> Result<PathBuf, Error> {
+        let outside = || {
+            format_err!(
+                "link `{}` points outside of the directory {}",
+                link.link,
+                base_dir.display()
+            )
+        };
+
+        let path = if link.link.starts_with("file:") {
+            &link.link["file:".len()..]
+        } else {
+            link.link.as_str()
+        };
+        let path = Path::new(path.trim_start_matches('/'));
+
+        // Only allow plain file and directory names, so that a link can not
+        // escape the base directory with `..`
+        if !path
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(outside());
+        }
+
+        // Symlinks in the base directory could still point outside of it
+        let canonicalize = |path: &Path| {
+            path.canonicalize()
+                .map_err(|e| format_err!("failed to read {}: {}", path.display(), e))
+        };
+        let base_dir = canonicalize(base_dir)?;
+        let path = canonicalize(&base_dir.join(path))?;
+        if !path.starts_with(&base_dir) {
+            return Err(outside());
+        }
+        Ok(path)
+    }
+
+    // Reads the file for `link`, which blocks
+    fn read(base_dir: &Path, link: &Link, max_file_size: Option<usize>) -> Result<Vec<u8>, Error> {
+        let path = Self::path(base_dir, link)?;
+        let size = fs::metadata(&path)
+            .map_err(|e| format_err!("failed to read {}: {}", path.display(), e))?
+            .len();
+        if let Some(max_file_size) = max_file_size {
+            if size > max_file_size as u64 {
+                return Err(FileSizeLimitExceeded {
+                    link: link.link.clone(),
+                    max_file_size,
+                    size,
+                }
+                .into());
+            }
+        }
+        fs::read(&path).map_err(|e| format_err!("failed to read {}: {}", path.display(), e))
+    }
+}
+
+#[async_trait]
+impl LinkResolver for FileLinkResolver {
+    fn with_timeout(self, _timeout: Duration) -> Self {
+        self
+    }
+
+    fn with_retries_config(self, _max_attempts: u32, _base_backoff: Duration) -> Self {
+        self
+    }
+
+    fn with_max_file_size(self, bytes: usize) -> Self {
+        Self {
+            max_file_size: Some(bytes),
+            ..self
+        }
+    }
+
+    async fn cat(&self, _logger: &Logger, link: &Link) -> Result<Vec<u8>, Error> {
+        let base_dir = self.base_dir.clone();
+        let link = link.clone();
+        let max_file_size = self.max_file_size;
+        tokio::task::spawn_blocking(move || Self::read(&base_dir, &link, max_file_size)).await?
+    }
+}
+
+/// Fetches the contents of URLs for `ArweaveLinkResolver`. This is
+/// implemented for `reqwest::Client`, and lets tests use a mock instead.
+#[async_trait]
+pub trait HttpClient: Send + Sync + 'static {
+    /// Fetches the body of a successful GET request for `url`. If the body
+    /// is larger than `max_size` bytes, fails with a `FileSizeLimitExceeded`
+    /// error for `url` as soon as that is known, without reading the rest of
+    /// the body. The `size` in the error is then the size announced by the
+    /// server, or the number of bytes read up to that point.
+    async fn get(&self, url: &str, max_size: Option<usize>) -> Result<Vec<u8>, Error>;
+}
+
+// Returns an error if `size` exceeds `max_size`
+fn check_http_body_size(url: &str, max_size: Option<usize>, size: u64) -> Result<(), Error> {
+    match max_size {
+        Some(max_file_size) if size > max_file_size as u64 => Err(FileSizeLimitExceeded {
+            link: url.to_owned(),
+            max_file_size,
+            size,
+        }
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+#[async_trait]
+impl HttpClient for reqwest::Client {
+    async fn get(&self, url: &str, max_size: Option<usize>) -> Result<Vec<u8>, Error> {
+        let mut response = reqwest::Client::get(self, url)
+            .send()
+            .await?
+            .error_for_status()?;
+        if let Some(size) = response.content_length() {
+            check_http_body_size(url, max_size, size)?;
+        }
+
+        // The announced size can not be trusted, and is not always there
+        let mut data = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            data.extend_from_slice(&chunk);
+            check_http_body_size(url, max_size, data.len() as u64)?;
+        }
+        Ok(data)
+    }
+}
+
+/// The timeout that `ArweaveLinkResolver` uses unless `with_timeout` is
+/// called.
+const DEFAULT_ARWEAVE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A `LinkResolver` for links of the form `ar://<transaction id>` to data
+/// stored on Arweave. The data is fetched from an Arweave gateway like
+/// `https://arweave.net`, which serves the data for a transaction at
+/// `<gateway>/<transaction id>`.
+pub struct ArweaveLinkResolver<C = reqwest::Client> {
+    client: C,
+    gateway: String,
+    timeout: Duration,
+    max_attempts: u32,
+    base_backoff: Duration,
+    max_file_size: Option<usize>,
+}
+
+impl<C: HttpClient> ArweaveLinkResolver<C> {
+    /// Fetch data from `gateway` using `client`.
+    pub fn new(client: C, gateway: impl Into<String>) -> Self {
+        Self {
+            client,
+            gateway: gateway.into().trim_end_matches('/').to_owned(),
+            timeout: DEFAULT_ARWEAVE_TIMEOUT,
+            max_attempts: 1,
+            base_backoff: DEFAULT_RETRY_BACKOFF,
+            max_file_size: None,
+        }
+    }
+
+    fn url(&self, link: &Link) -> Result<String, Error> {
+        if !link.link.starts_with("ar://") || link.link.len() == "ar://".len() {
+            return Err(format_err!("link `{}` is not an Arweave link", link.link));
+        }
+        Ok(format!("{}/{}", self.gateway, &link.link["ar://".len()..]))
+    }
+}
+
+#[async_trait]
+impl<C: HttpClient> LinkResolver for ArweaveLinkResolver<C> {
+    fn with_timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+
+    fn with_retries_config(self, max_attempts: u32, base_backoff: Duration) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            base_backoff,
+            ..self
+        }
+    }
+
+    fn with_max_file_size(self, bytes: usize) -> Self {
+        Self {
+            max_file_size: Some(bytes),
+            ..self
+        }
+    }
+
+    async fn cat(&self, logger: &Logger, link: &Link) -> Result<Vec<u8>, Error> {
+        let url = self.url(link)?;
+        let client = &self.client;
+        let max_file_size = self.max_file_size;
+        retry("arweave.get", logger)
+            .limit(self.max_attempts as usize)
+            .backoff(self.base_backoff)
+            .timeout(self.timeout)
+            .run(move || {
+                let url = url.clone();
+                async move { client.get(&url, max_file_size).await }
+                    .boxed()
+                    .compat()
+            })
+            .compat()
+            .await
+            .map_err(|e| link_timeout_error(e, &link.link, self.timeout))
+            .map_err(|e| match e.downcast::<FileSizeLimitExceeded>() {
+                // Report the link, not the gateway URL it was fetched from
+                Ok(e) => FileSizeLimitExceeded {
+                    link: link.link.clone(),
+                    ..e
+                }
+                .into(),
+                Err(e) => e,
+            })
+    }
 }
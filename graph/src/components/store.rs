@@ -1360,6 +1360,13 @@ pub trait SubgraphDeploymentStore: Send + Sync + 'static {
     /// Return the name of the network that the subgraph is indexing from. The
     /// names returned are things like `mainnet` or `ropsten`
     fn network_name(&self, subgraph_id: &SubgraphDeploymentId) -> Result<Option<String>, Error>;
+
+    /// Return the subgraph that the subgraph was grafted onto, or `None` if
+    /// it was not grafted
+    fn graft_base(
+        &self,
+        subgraph_id: &SubgraphDeploymentId,
+    ) -> Result<Option<SubgraphDeploymentId>, Error>;
 }
 
 /// Common trait for blockchain store implementations.
@@ -10,6 +10,7 @@ use std::string::FromUtf8Error;
 
 use crate::components::store::StoreError;
 use crate::data::graphql::SerializableValue;
+use crate::data::store::scalar::ParseBytesError;
 use crate::data::subgraph::*;
 
 /// Error caused while executing a [Query](struct.Query.html).
@@ -225,6 +226,12 @@ impl From<FromHexError> for QueryExecutionError {
     }
 }
 
+impl From<ParseBytesError> for QueryExecutionError {
+    fn from(e: ParseBytesError) -> Self {
+        QueryExecutionError::ValueParseError("Bytes".to_string(), e.to_string())
+    }
+}
+
 impl From<num_bigint::ParseBigIntError> for QueryExecutionError {
     fn from(e: num_bigint::ParseBigIntError) -> Self {
         QueryExecutionError::ValueParseError("BigInt".to_string(), format!("{}", e))
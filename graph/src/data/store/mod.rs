@@ -1,9 +1,10 @@
-use failure::Error;
+use failure::{Error, Fail};
 use graphql_parser::query;
 use graphql_parser::schema;
 use serde::de;
 use serde::{Deserialize, Serialize};
 use stable_hash::prelude::*;
+use stable_hash::utils::stable_hash_with_hasher;
 use std::collections::{BTreeMap, HashMap};
 use std::convert::TryFrom;
 use std::fmt;
@@ -14,6 +15,7 @@ use std::str::FromStr;
 use crate::data::subgraph::SubgraphDeploymentId;
 use crate::prelude::{format_err, EntityKey, QueryExecutionError};
 use crate::util::lfu_cache::CacheWeight;
+use twox_hash::XxHash64;
 
 /// Custom scalars in GraphQL.
 pub mod scalar;
@@ -469,6 +471,19 @@ where
     }
 }
 
+/// The error returned by the typed getters of `Entity`, like
+/// `Entity::get_bigint`, when an attribute has a different type
+#[derive(Fail, Debug, Clone, PartialEq, Eq)]
+#[fail(
+    display = "attribute `{}` has type {} but {} was expected",
+    attribute, actual, expected
+)]
+pub struct EntityTypeError {
+    pub attribute: String,
+    pub expected: &'static str,
+    pub actual: String,
+}
+
 // Note: Do not modify fields without making a backward compatible change to the
 //  StableHash impl (below) An entity is represented as a map of attribute names
 //  to values.
@@ -482,6 +497,38 @@ impl StableHash for Entity {
     }
 }
 
+/// A collection of `(entity_type, entity)` pairs whose `StableHash` does not
+/// depend on the order of the collection. The entities are hashed in the
+/// order of their type and id, which makes it possible to compare all
+/// entities of a deployment across indexers. Entities with the same type and
+/// id, or without an id, are ordered by their own stable hash.
+pub struct EntitySet<'a>(pub Vec<(&'a str, &'a Entity)>);
+
+impl StableHash for EntitySet<'_> {
+    fn stable_hash(&self, mut sequence_number: impl SequenceNumber, state: &mut impl StableHasher) {
+        let mut entities: Vec<_> = self
+            .0
+            .iter()
+            .map(|(entity_type, entity)| {
+                let key = (
+                    *entity_type,
+                    entity.id().ok(),
+                    stable_hash_with_hasher::<XxHash64, _>(*entity),
+                );
+                (key, *entity)
+            })
+            .collect();
+        entities.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        for ((entity_type, _, _), entity) in &entities {
+            let mut pair_sequence_number = sequence_number.next_child();
+            entity_type.stable_hash(pair_sequence_number.next_child(), state);
+            entity.stable_hash(pair_sequence_number.next_child(), state);
+        }
+        entities.len().stable_hash(sequence_number, state);
+    }
+}
+
 #[macro_export]
 macro_rules! entity {
     ($($name:ident: $value:expr,)*) => {
@@ -513,11 +560,62 @@ impl Entity {
         }
     }
 
+    /// Returns the value of the `BigInt` attribute `key`, `None` if it is
+    /// not set or null, or an error if it has a different type.
+    pub fn get_bigint(&self, key: &str) -> Result<Option<scalar::BigInt>, EntityTypeError> {
+        self.get_typed(key, "BigInt", Value::as_bigint)
+    }
+
+    /// Like `get_bigint`, for a `BigDecimal` attribute
+    pub fn get_bigdecimal(&self, key: &str) -> Result<Option<scalar::BigDecimal>, EntityTypeError> {
+        self.get_typed(key, "BigDecimal", Value::as_big_decimal)
+    }
+
+    /// Like `get_bigint`, for a `String` attribute
+    pub fn get_string(&self, key: &str) -> Result<Option<String>, EntityTypeError> {
+        self.get_typed(key, "String", Value::as_string)
+    }
+
+    /// Like `get_bigint`, for a `Bytes` attribute
+    pub fn get_bytes(&self, key: &str) -> Result<Option<scalar::Bytes>, EntityTypeError> {
+        self.get_typed(key, "Bytes", Value::as_bytes)
+    }
+
+    /// Like `get_bigint`, for a `Boolean` attribute
+    pub fn get_bool(&self, key: &str) -> Result<Option<bool>, EntityTypeError> {
+        self.get_typed(key, "Boolean", Value::as_bool)
+    }
+
+    fn get_typed<T>(
+        &self,
+        key: &str,
+        expected: &'static str,
+        convert: fn(Value) -> Option<T>,
+    ) -> Result<Option<T>, EntityTypeError> {
+        match self.get(key) {
+            None | Some(Value::Null) => Ok(None),
+            Some(value) => convert(value.clone())
+                .map(Some)
+                .ok_or_else(|| EntityTypeError {
+                    attribute: key.to_owned(),
+                    expected,
+                    actual: value.type_name(),
+                }),
+        }
+    }
+
     /// Convenience method to save having to `.into()` the arguments.
     pub fn set(&mut self, name: impl Into<Attribute>, value: impl Into<Value>) -> Option<Value> {
         self.insert(name.into(), value.into())
     }
 
+    /// Sets `name` to `value` and returns the entity, so that entities can be
+    /// built like `Entity::new().with("id", "x").with("count", 1)`.
+    pub fn with(mut self, name: impl Into<Attribute>, value: impl Into<Value>) -> Entity {
+        self.set(name, value);
+        self
+    }
+
     /// Merges an entity update `update` into this entity.
     ///
     /// If a key exists in both entities, the value from `update` is chosen.
@@ -529,6 +627,14 @@ impl Entity {
         }
     }
 
+    /// Like `merge`, but consumes this entity and returns the merged entity.
+    /// As with `merge`, a `Value::Null` in `update` sets the key to
+    /// `Value::Null` rather than removing it.
+    pub fn merged(mut self, update: Entity) -> Entity {
+        self.merge(update);
+        self
+    }
+
     /// Merges an entity update `update` into this entity, removing `Value::Null` values.
     ///
     /// If a key exists in both entities, the value from `update` is chosen.
@@ -655,3 +761,123 @@ fn value_bigint() {
     );
     assert_eq!(query::Value::from(from_query), graphql_value);
 }
+
+#[test]
+fn entity_set_stable_hash() {
+    use stable_hash::utils::stable_hash_with_hasher;
+    use twox_hash::XxHash64;
+
+    let alice = entity! { id: "alice", name: "Alice" };
+    let bob = entity! { id: "bob", name: "Bob" };
+    let thing = entity! { id: "alice", weight: 5 };
+
+    let hash = |entities: Vec<(&str, &Entity)>| {
+        stable_hash_with_hasher::<XxHash64, _>(&EntitySet(entities))
+    };
+
+    let forward = hash(vec![("User", &alice), ("User", &bob), ("Thing", &thing)]);
+    let backward = hash(vec![("Thing", &thing), ("User", &bob), ("User", &alice)]);
+    assert_eq!(forward, backward);
+
+    let fewer = hash(vec![("User", &alice), ("User", &bob)]);
+    assert_ne!(forward, fewer);
+
+    // Entities with the same type and id, or without an id, hash the same
+    // in any order
+    let alias = entity! { id: "alice", name: "Alias" };
+    let anonymous = entity! { name: "Anonymous" };
+    let nameless = entity! { weight: 7 };
+    let forward = hash(vec![
+        ("User", &alice),
+        ("User", &alias),
+        ("User", &anonymous),
+        ("User", &nameless),
+    ]);
+    let backward = hash(vec![
+        ("User", &nameless),
+        ("User", &anonymous),
+        ("User", &alias),
+        ("User", &alice),
+    ]);
+    assert_eq!(forward, backward);
+}
+
+#[test]
+fn entity_typed_getters() {
+    let entity = entity! {
+        id: "thing",
+        count: scalar::BigInt::from(7),
+        price: scalar::BigDecimal::from(1.5),
+        data: Value::Bytes(scalar::Bytes::from(&[1u8, 2][..])),
+        active: true,
+        gone: Value::Null,
+    };
+
+    assert_eq!(Ok(Some("thing".to_owned())), entity.get_string("id"));
+    assert_eq!(
+        Ok(Some(scalar::BigInt::from(7))),
+        entity.get_bigint("count")
+    );
+    assert_eq!(
+        Ok(Some(scalar::BigDecimal::from(1.5))),
+        entity.get_bigdecimal("price")
+    );
+    assert_eq!(
+        Ok(Some(scalar::Bytes::from(&[1u8, 2][..]))),
+        entity.get_bytes("data")
+    );
+    assert_eq!(Ok(Some(true)), entity.get_bool("active"));
+
+    // Missing and null attributes are `None`
+    assert_eq!(Ok(None), entity.get_bigint("missing"));
+    assert_eq!(Ok(None), entity.get_bigint("gone"));
+
+    // Attributes of a different type are an error
+    let err = entity.get_bigint("id").unwrap_err();
+    assert_eq!(
+        EntityTypeError {
+            attribute: "id".to_owned(),
+            expected: "BigInt",
+            actual: "String".to_owned(),
+        },
+        err
+    );
+    assert_eq!(
+        "attribute `id` has type String but BigInt was expected",
+        err.to_string()
+    );
+    assert!(entity.get_string("count").is_err());
+}
+
+#[test]
+fn entity_merge() {
+    let base = entity! { id: "alice", name: "Alice", age: 30 };
+    let update = entity! { id: "alice", age: 31, nickname: Value::Null };
+
+    // Fields in the update overwrite, other fields are preserved, and an
+    // explicit null is kept as a null
+    let merged = base.clone().merged(update.clone());
+    assert_eq!(
+        entity! { id: "alice", name: "Alice", age: 31, nickname: Value::Null },
+        merged
+    );
+
+    let mut merged = base.clone();
+    merged.merge(update.clone());
+    assert_eq!(base.clone().merged(update.clone()), merged);
+
+    // An explicit null removes the field when merging with
+    // `merge_remove_null_fields`
+    let mut merged = entity! { id: "alice", nickname: "Al" };
+    merged.merge_remove_null_fields(update);
+    assert_eq!(entity! { id: "alice", age: 31 }, merged);
+}
+
+#[test]
+fn entity_with() {
+    let mut imperative = Entity::new();
+    imperative.set("id", "x");
+    imperative.set("count", 1);
+
+    assert_eq!(imperative, Entity::new().with("id", "x").with("count", 1));
+}
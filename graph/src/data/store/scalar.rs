@@ -1,6 +1,13 @@
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::Pg;
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::Numeric;
 use failure::Fail;
 use hex;
+use lazy_static::lazy_static;
 use num_bigint;
+use num_integer::Integer;
+use num_traits::{One, Zero};
 use serde::{self, Deserialize, Serialize};
 use web3::types::*;
 
@@ -10,28 +17,79 @@ use stable_hash::{
 };
 use std::convert::{TryFrom, TryInto};
 use std::fmt::{self, Display, Formatter};
-use std::ops::{Add, Div, Mul, Rem, Sub};
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::iter::{Product, Sum};
+use std::ops::{
+    Add, AddAssign, BitAnd, BitOr, BitXor, Deref, Div, DivAssign, Mul, MulAssign, Neg, Rem,
+    RemAssign, Shl, Shr, Sub, SubAssign,
+};
 use std::str::FromStr;
 
-pub use num_bigint::Sign as BigIntSign;
+pub use num_bigint::{ParseBigIntError, Sign as BigIntSign};
 
 // Caveat: The exponent is currently an i64 and may overflow.
 // See https://github.com/akubera/bigdecimal-rs/issues/54.
-pub type BigDecimal = bigdecimal::BigDecimal;
+//
+// This is a newtype around `bigdecimal::BigDecimal` rather than an alias so
+// that we can implement standard traits for it.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BigDecimal(bigdecimal::BigDecimal);
 
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct BigInt(num_bigint::BigInt);
 
+/// The version of the layout in which `BigInt` and `BigDecimal` values are
+/// hashed. The version is hashed as the first child of each value, and any
+/// change to the layout must bump it so that values hashed with the new
+/// layout can not collide with values hashed with an older one.
+///
+/// Version 0 is the layout from before versions were introduced, in which
+/// the version is not hashed and `BigInt` hashes like the primitive integer
+/// types. It is still the version that is used, since every node has to
+/// hash the same way for proofs of indexing to agree.
+///
+/// Version 1 hashes the version as the first child, followed by the fields
+/// of version 0 as separate children. Switching to it changes the hash of
+/// every `BigInt` and `BigDecimal`, and with it every proof of indexing of a
+/// block that stores one. It must therefore only be made the default in a
+/// release that announces it in `NEWS.md`, so that all indexers that compare
+/// proofs of indexing upgrade together.
+const BIG_NUMBER_STABLE_HASH_VERSION: u64 = 0;
+
 pub(crate) fn big_decimal_stable_hash(
     decimal: &BigDecimal,
+    sequence_number: impl SequenceNumber,
+    state: &mut impl StableHasher,
+) {
+    big_decimal_stable_hash_with_version(
+        decimal,
+        BIG_NUMBER_STABLE_HASH_VERSION,
+        sequence_number,
+        state,
+    )
+}
+
+fn big_decimal_stable_hash_with_version(
+    decimal: &BigDecimal,
+    version: u64,
     mut sequence_number: impl SequenceNumber,
     state: &mut impl StableHasher,
 ) {
     let (int, exp) = decimal.as_bigint_and_exponent();
-    // This only allows for backward compatible changes between
-    // BigDecimal and unsigned ints
-    exp.stable_hash(sequence_number.next_child(), state);
-    big_int_stable_hash(&int, sequence_number, state);
+    match version {
+        0 => {
+            // This only allows for backward compatible changes between
+            // BigDecimal and unsigned ints
+            exp.stable_hash(sequence_number.next_child(), state);
+            big_int_stable_hash(&int, sequence_number, state);
+        }
+        _ => {
+            version.stable_hash(sequence_number.next_child(), state);
+            exp.stable_hash(sequence_number.next_child(), state);
+            big_int_stable_hash(&int, sequence_number.next_child(), state);
+        }
+    }
 }
 
 fn big_int_stable_hash(
@@ -46,9 +104,29 @@ fn big_int_stable_hash(
     .stable_hash(sequence_number, state)
 }
 
+fn big_int_stable_hash_with_version(
+    int: &num_bigint::BigInt,
+    version: u64,
+    mut sequence_number: impl SequenceNumber,
+    state: &mut impl StableHasher,
+) {
+    match version {
+        0 => big_int_stable_hash(int, sequence_number, state),
+        _ => {
+            version.stable_hash(sequence_number.next_child(), state);
+            big_int_stable_hash(int, sequence_number.next_child(), state);
+        }
+    }
+}
+
 impl StableHash for BigInt {
     fn stable_hash(&self, sequence_number: impl SequenceNumber, state: &mut impl StableHasher) {
-        big_int_stable_hash(&self.0, sequence_number, state);
+        big_int_stable_hash_with_version(
+            &self.0,
+            BIG_NUMBER_STABLE_HASH_VERSION,
+            sequence_number,
+            state,
+        );
     }
 }
 
@@ -58,8 +136,36 @@ pub enum BigIntOutOfRangeError {
     Negative,
     #[fail(display = "BigInt value is too large for type")]
     Overflow,
+    #[fail(display = "Cannot convert BigDecimal with a fractional part into BigInt")]
+    Fractional,
 }
 
+#[derive(Fail, Debug, PartialEq)]
+pub enum ArithmeticError {
+    #[fail(display = "Division by zero")]
+    DivisionByZero,
+    #[fail(display = "Square root of a negative number")]
+    NegativeSquareRoot,
+    #[fail(display = "Negative exponent")]
+    NegativeExponent,
+    #[fail(display = "Exponent is out of range")]
+    ExponentOutOfRange,
+    #[fail(display = "Result is too large")]
+    Overflow,
+    #[fail(display = "Value is not a finite number")]
+    NotFinite,
+    #[fail(display = "Result can not be represented without losing precision")]
+    PrecisionLoss,
+    #[fail(display = "Value is negative")]
+    NegativeValue,
+}
+
+/// The error returned when a JSON value that is neither a number nor a
+/// numeric string is converted into a `BigInt` or `BigDecimal`
+#[derive(Fail, Debug, PartialEq)]
+#[fail(display = "expected a number or a numeric string, got `{}`", _0)]
+pub struct InvalidJsonNumber(pub String);
+
 impl<'a> TryFrom<&'a BigInt> for u64 {
     type Error = BigIntOutOfRangeError;
     fn try_from(value: &'a BigInt) -> Result<u64, BigIntOutOfRangeError> {
@@ -91,7 +197,46 @@ impl TryFrom<BigInt> for u64 {
     }
 }
 
+macro_rules! impl_try_from_big_int {
+    ($ty:ty, $to:ident) => {
+        impl<'a> TryFrom<&'a BigInt> for $ty {
+            type Error = BigIntOutOfRangeError;
+            fn try_from(value: &'a BigInt) -> Result<$ty, BigIntOutOfRangeError> {
+                use num_traits::ToPrimitive;
+
+                value.0.$to().ok_or_else(|| {
+                    if value.0.sign() == BigIntSign::Minus && <$ty>::min_value() == 0 {
+                        BigIntOutOfRangeError::Negative
+                    } else {
+                        BigIntOutOfRangeError::Overflow
+                    }
+                })
+            }
+        }
+
+        impl TryFrom<BigInt> for $ty {
+            type Error = BigIntOutOfRangeError;
+            fn try_from(value: BigInt) -> Result<$ty, BigIntOutOfRangeError> {
+                (&value).try_into()
+            }
+        }
+    };
+}
+
+impl_try_from_big_int!(i64, to_i64);
+impl_try_from_big_int!(i32, to_i32);
+impl_try_from_big_int!(u32, to_u32);
+impl_try_from_big_int!(u8, to_u8);
+impl_try_from_big_int!(i128, to_i128);
+impl_try_from_big_int!(u128, to_u128);
+
 impl BigInt {
+    /// Returns `10^exp`, the factor that scales a token amount with `exp`
+    /// decimals. Common exponents are looked up rather than computed.
+    pub fn ten_pow(exp: u8) -> BigInt {
+        BigInt(ten_pow(exp as u64))
+    }
+
     pub fn from_unsigned_bytes_le(bytes: &[u8]) -> Self {
         BigInt(num_bigint::BigInt::from_bytes_le(
             num_bigint::Sign::Plus,
@@ -99,6 +244,96 @@ impl BigInt {
         ))
     }
 
+    /// Parses `s` as a number in the given `radix`. For radix 16, the digits
+    /// may be prefixed with `0x`, e.g. `0xff` or `-0xff`.
+    pub fn from_str_radix(s: &str, radix: u32) -> Result<BigInt, ParseBigIntError> {
+        use num_traits::Num;
+
+        if radix == 16 {
+            let (sign, digits) = if s.starts_with('-') {
+                ("-", &s[1..])
+            } else {
+                ("", s)
+            };
+            if digits.starts_with("0x") {
+                let s = format!("{}{}", sign, &digits[2..]);
+                return num_bigint::BigInt::from_str_radix(&s, radix).map(BigInt);
+            }
+        }
+        num_bigint::BigInt::from_str_radix(s, radix).map(BigInt)
+    }
+
+    /// Formats `self` in the given `radix` with lowercase digits and a
+    /// leading `-` for negative values, but without any prefix. This is the
+    /// inverse of `from_str_radix`. Panics if `radix` is not in `2..=36`.
+    pub fn to_str_radix(&self, radix: u32) -> String {
+        assert!(
+            (2..=36).contains(&radix),
+            "radix must be between 2 and 36, but is {}",
+            radix
+        );
+        self.0.to_str_radix(radix)
+    }
+
+    /// Returns the sign and the digits of `self` in the given `radix`,
+    /// most significant digit first. Each digit is stored in one byte.
+    /// Panics if `radix` is not in `2..=256`.
+    pub fn to_radix_be(&self, radix: u32) -> (BigIntSign, Vec<u8>) {
+        assert!(
+            (2..=256).contains(&radix),
+            "radix must be between 2 and 256, but is {}",
+            radix
+        );
+        self.0.to_radix_be(radix)
+    }
+
+    /// Like `to_radix_be`, but with the least significant digit first.
+    /// Panics if `radix` is not in `2..=256`.
+    pub fn to_radix_le(&self, radix: u32) -> (BigIntSign, Vec<u8>) {
+        assert!(
+            (2..=256).contains(&radix),
+            "radix must be between 2 and 256, but is {}",
+            radix
+        );
+        self.0.to_radix_le(radix)
+    }
+
+    /// The decimal digits of the magnitude of `self`, most significant
+    /// first; the sign is ignored. Zero is `[0]`.
+    pub fn decimal_digits(&self) -> Vec<u8> {
+        self.to_radix_be(10).1
+    }
+
+    /// Parses a hexadecimal number like `0xff` or `-0xff`. The `0x` prefix
+    /// is optional, so `ff` is accepted as well; this is the same as
+    /// `from_str_radix(s, 16)`.
+    pub fn from_hex(s: &str) -> Result<BigInt, ParseBigIntError> {
+        BigInt::from_str_radix(s, 16)
+    }
+
+    /// Converts a JSON number or a string holding a decimal integer, as
+    /// found in JSON-RPC responses, into a `BigInt`
+    pub fn from_json_value(value: &serde_json::Value) -> Result<BigInt, InvalidJsonNumber> {
+        match value {
+            serde_json::Value::Number(n) => BigInt::from_str(&n.to_string()).ok(),
+            serde_json::Value::String(s) => BigInt::from_str(s).ok(),
+            _ => None,
+        }
+        .ok_or_else(|| InvalidJsonNumber(value.to_string()))
+    }
+
+    /// Converts `self` into a JSON string. JSON numbers are avoided since
+    /// many JSON parsers read them as `f64` and lose precision.
+    pub fn to_json_value(&self) -> serde_json::Value {
+        serde_json::Value::String(self.to_string())
+    }
+
+    /// Formats `self` as a `0x`-prefixed lowercase hexadecimal number, with
+    /// a leading `-` for negative values, e.g. `-0xff`.
+    pub fn to_hex(&self) -> String {
+        format!("{:#x}", self.0)
+    }
+
     pub fn from_signed_bytes_le(bytes: &[u8]) -> Self {
         BigInt(num_bigint::BigInt::from_signed_bytes_le(bytes))
     }
@@ -107,6 +342,16 @@ impl BigInt {
         self.0.to_bytes_le()
     }
 
+    /// Calls `f` with the sign and the little-endian bytes of the magnitude
+    /// of `self`, the same as what `to_bytes_le` returns. `num_bigint` does
+    /// not give access to its internal digits, which are `u32`s, so this
+    /// still allocates a buffer for the bytes on every call; it only saves
+    /// callers that merely look at the bytes from holding on to it.
+    pub fn with_bytes_le<R>(&self, f: impl FnOnce(BigIntSign, &[u8]) -> R) -> R {
+        let (sign, bytes) = self.0.to_bytes_le();
+        f(sign, &bytes)
+    }
+
     pub fn to_bytes_be(&self) -> (BigIntSign, Vec<u8>) {
         self.0.to_bytes_be()
     }
@@ -120,6 +365,15 @@ impl BigInt {
         self.try_into().unwrap()
     }
 
+    /// Converts to the nearest `f64`, or returns `None` if the value is
+    /// outside of the range of `f64`. Integers with an absolute value above
+    /// `2^53` can not be represented exactly and will lose precision.
+    pub fn to_f64(&self) -> Option<f64> {
+        use num_traits::ToPrimitive;
+
+        self.0.to_f64().filter(|f| f.is_finite())
+    }
+
     pub fn from_unsigned_u256(n: &U256) -> Self {
         let mut bytes: [u8; 32] = [0; 32];
         n.to_little_endian(&mut bytes);
@@ -134,7 +388,7 @@ impl BigInt {
 
     pub fn to_signed_u256(&self) -> U256 {
         let bytes = self.to_signed_bytes_le();
-        if self < &BigInt::from(0) {
+        if self.is_negative() {
             assert!(
                 bytes.len() <= 32,
                 "BigInt value does not fit into signed U256"
@@ -147,6 +401,40 @@ impl BigInt {
         }
     }
 
+    /// Like `to_signed_u256`, but returns an `Overflow` error instead of
+    /// panicking if `self` does not fit into an int256.
+    pub fn try_to_signed_u256(&self) -> Result<U256, ArithmeticError> {
+        let bytes = self.to_signed_bytes_le();
+        if bytes.len() > 32 {
+            return Err(ArithmeticError::Overflow);
+        }
+        let fill = if self.is_negative() { 255 } else { 0 };
+        let mut i_bytes: [u8; 32] = [fill; 32];
+        i_bytes[..bytes.len()].copy_from_slice(&bytes);
+        Ok(U256::from_little_endian(&i_bytes))
+    }
+
+    /// Interprets `n` as a two's complement signed 128-bit integer (aka
+    /// int128 in Solidity).
+    pub fn from_signed_u128(n: &U128) -> Self {
+        let mut bytes: [u8; 16] = [0; 16];
+        n.to_little_endian(&mut bytes);
+        BigInt::from_signed_bytes_le(&bytes)
+    }
+
+    /// Returns the two's complement representation of `self` as a signed
+    /// 128-bit integer, or an error if `self` does not fit into an int128.
+    pub fn to_signed_u128(&self) -> Result<U128, BigIntOutOfRangeError> {
+        let bytes = self.to_signed_bytes_le();
+        if bytes.len() > 16 {
+            return Err(BigIntOutOfRangeError::Overflow);
+        }
+        let fill = if self.is_negative() { 255 } else { 0 };
+        let mut i_bytes: [u8; 16] = [fill; 16];
+        i_bytes[..bytes.len()].copy_from_slice(&bytes);
+        Ok(U128::from_little_endian(&i_bytes))
+    }
+
     pub fn to_unsigned_u256(&self) -> U256 {
         let (sign, bytes) = self.to_bytes_le();
         assert!(
@@ -157,28 +445,267 @@ impl BigInt {
         U256::from_little_endian(&bytes)
     }
 
-    pub fn to_big_decimal(self, exp: BigInt) -> BigDecimal {
-        let bytes = exp.to_signed_bytes_le();
+    /// Like `to_unsigned_u256`, but returns a `NegativeValue` error for
+    /// negative values and an `Overflow` error for values that do not fit
+    /// into 256 bits instead of panicking.
+    pub fn try_to_unsigned_u256(&self) -> Result<U256, ArithmeticError> {
+        let (sign, bytes) = self.to_bytes_le();
+        if sign == BigIntSign::Minus {
+            return Err(ArithmeticError::NegativeValue);
+        }
+        if bytes.len() > 32 {
+            return Err(ArithmeticError::Overflow);
+        }
+        Ok(U256::from_little_endian(&bytes))
+    }
 
+    /// Returns `self * 10^exp`. Panics if `exp` is out of range; see
+    /// `try_to_big_decimal`.
+    pub fn to_big_decimal(self, exp: BigInt) -> BigDecimal {
         // The hope here is that bigdecimal switches to BigInt exponents. Until
         // then, a panic is fine since this is only used in mappings.
-        if bytes.len() > 8 {
-            panic!("big decimal exponent does not fit in i64")
-        }
-        let mut byte_array = if exp >= 0.into() { [0; 8] } else { [255; 8] };
-        byte_array[..bytes.len()].copy_from_slice(&bytes);
-        BigDecimal::new(self.0, -i64::from_le_bytes(byte_array))
+        self.try_to_big_decimal(exp)
+            .expect("big decimal exponent does not fit in i64")
+    }
+
+    /// Returns `self * 10^exp`, or an error if the exponent does not fit
+    /// into the `i64` scale of a `BigDecimal`.
+    pub fn try_to_big_decimal(self, exp: BigInt) -> Result<BigDecimal, ArithmeticError> {
+        let scale = i64::try_from(&exp)
+            .ok()
+            .and_then(|exp| exp.checked_neg())
+            .ok_or(ArithmeticError::ExponentOutOfRange)?;
+        Ok(BigDecimal::new(self.0, scale))
     }
 
-    pub fn pow(self, exponent: u8) -> Self {
+    /// Raises `self` to the power `exponent`. Note that the result can get
+    /// very large; use `checked_pow` to put a bound on its size.
+    pub fn pow(self, exponent: u32) -> Self {
         use num_traits::pow::Pow;
 
         BigInt(self.0.pow(&exponent))
     }
 
+    /// Like `pow`, but returns an error instead of computing a result that
+    /// has more than `max_bits` bits.
+    pub fn checked_pow(self, exponent: u32, max_bits: u64) -> Result<BigInt, ArithmeticError> {
+        let bits = self.bits();
+        // `|self|^exponent` has at least `(bits - 1) * exponent + 1` bits,
+        // which lets us reject results that are too large without
+        // computing them. For `|self| <= 1`, `|result| <= 1`, too
+        if bits > 1 && exponent > 0 && (bits - 1).saturating_mul(exponent.into()) >= max_bits {
+            return Err(ArithmeticError::Overflow);
+        }
+        let result = self.pow(exponent);
+        if result.bits() > max_bits {
+            return Err(ArithmeticError::Overflow);
+        }
+        Ok(result)
+    }
+
+    /// Like `pow`, but returns an error if the absolute value of the result
+    /// would be larger than `ceiling`, e.g. `2^256 - 1` for values that must
+    /// fit into 256 bits. The result is computed by repeated squaring and
+    /// the error is returned as soon as an intermediate value exceeds
+    /// `ceiling`, without computing the full result. Since intermediate
+    /// values never exceed the absolute value of the result, this does not
+    /// reject results that fit.
+    pub fn checked_pow_bounded(
+        self,
+        exponent: u32,
+        ceiling: &BigInt,
+    ) -> Result<BigInt, ArithmeticError> {
+        use num_traits::Signed;
+
+        let negative = self.0.is_negative() && exponent % 2 == 1;
+        let mut base = self.0.abs();
+        let mut result = num_bigint::BigInt::one();
+        let mut exponent = exponent;
+        loop {
+            if exponent & 1 == 1 {
+                result *= &base;
+                if result > ceiling.0 {
+                    return Err(ArithmeticError::Overflow);
+                }
+            }
+            exponent >>= 1;
+            if exponent == 0 {
+                break;
+            }
+            // `base` is a factor of the result, unless it is 0
+            base = &base * &base;
+            if base > ceiling.0 {
+                return Err(ArithmeticError::Overflow);
+            }
+        }
+        if result > ceiling.0 {
+            return Err(ArithmeticError::Overflow);
+        }
+        Ok(BigInt(if negative { -result } else { result }))
+    }
+
     pub fn bits(&self) -> u64 {
         self.0.bits() as u64
     }
+
+    /// Returns the number of bytes needed to hold the magnitude of `self`,
+    /// which is at least 1, even for zero. Since the sign is not taken into
+    /// account, the two's complement representation of a negative number may
+    /// need one more byte.
+    pub fn byte_len(&self) -> usize {
+        ((self.bits() as usize + 7) / 8).max(1)
+    }
+
+    /// Whether bit `n` of `self` is set, counting from the least significant
+    /// bit. Negative values are treated as if they were in two's complement
+    /// with infinite sign extension, like `to_signed_bytes_le`, so that bits
+    /// beyond the length of a negative value are set.
+    pub fn bit(&self, n: u64) -> bool {
+        let bytes = self.to_signed_bytes_le();
+        match bytes.get((n / 8) as usize) {
+            Some(byte) => byte & (1 << (n % 8)) != 0,
+            None => self.is_negative(),
+        }
+    }
+
+    /// Sets bit `n` of `self` to `value`, using the same two's complement
+    /// representation as `bit`. Since bits beyond the length of a value are
+    /// copies of its sign bit, changing one of them never changes the sign.
+    pub fn set_bit(&mut self, n: u64, value: bool) {
+        let mut bytes = self.to_signed_bytes_le();
+        let fill = if self.is_negative() { 255 } else { 0 };
+        // Keep at least one byte of sign extension above bit `n`
+        let len = bytes.len().max((n / 8) as usize + 2);
+        bytes.resize(len, fill);
+
+        let mask = 1 << (n % 8);
+        let byte = &mut bytes[(n / 8) as usize];
+        if value {
+            *byte |= mask;
+        } else {
+            *byte &= !mask;
+        }
+        *self = BigInt::from_signed_bytes_le(&bytes);
+    }
+
+    /// Returns the integer square root, i.e., the largest integer whose
+    /// square is less than or equal to `self`.
+    pub fn sqrt(&self) -> Result<BigInt, ArithmeticError> {
+        if self.is_negative() {
+            return Err(ArithmeticError::NegativeSquareRoot);
+        }
+        Ok(BigInt(self.0.sqrt()))
+    }
+
+    /// Returns the greatest common divisor, which is always non-negative.
+    pub fn gcd(&self, other: &BigInt) -> BigInt {
+        BigInt(self.0.gcd(&other.0))
+    }
+
+    /// Returns the least common multiple, which is always non-negative. The
+    /// least common multiple of any number and zero is zero.
+    pub fn lcm(&self, other: &BigInt) -> BigInt {
+        BigInt(self.0.lcm(&other.0))
+    }
+
+    /// Returns `self ^ exponent mod modulus`. For a positive modulus, the
+    /// result is always in the range `[0, modulus)`, even if `self` is
+    /// negative.
+    pub fn modpow(&self, exponent: &BigInt, modulus: &BigInt) -> Result<BigInt, ArithmeticError> {
+        if modulus.is_zero() {
+            return Err(ArithmeticError::DivisionByZero);
+        }
+        if exponent.is_negative() {
+            return Err(ArithmeticError::NegativeExponent);
+        }
+        Ok(BigInt(self.0.modpow(&exponent.0, &modulus.0)))
+    }
+
+    pub fn abs(&self) -> BigInt {
+        use num_traits::Signed;
+
+        BigInt(self.0.abs())
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.sign() == BigIntSign::NoSign
+    }
+
+    pub fn is_positive(&self) -> bool {
+        self.0.sign() == BigIntSign::Plus
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.0.sign() == BigIntSign::Minus
+    }
+
+    pub fn min(self, other: BigInt) -> BigInt {
+        std::cmp::min(self, other)
+    }
+
+    pub fn max(self, other: BigInt) -> BigInt {
+        std::cmp::max(self, other)
+    }
+
+    /// Restricts `self` to the interval `[lo, hi]`. Panics if `lo > hi`.
+    pub fn clamp(self, lo: BigInt, hi: BigInt) -> BigInt {
+        assert!(lo <= hi, "clamp: lo `{}` is greater than hi `{}`", lo, hi);
+        self.max(lo).min(hi)
+    }
+
+    /// The same as `clamp`, for code that keeps a value like a counter
+    /// within bounds and wants to make that intent clear.
+    pub fn saturating_clamp(self, lo: BigInt, hi: BigInt) -> BigInt {
+        self.clamp(lo, hi)
+    }
+
+    /// Converts `self` to a `u64`, returning 0 for negative values and
+    /// `u64::MAX` for values that are too large, rather than failing like
+    /// `u64::try_from`.
+    pub fn saturating_to_u64(&self) -> u64 {
+        match u64::try_from(self) {
+            Ok(n) => n,
+            Err(BigIntOutOfRangeError::Negative) => 0,
+            Err(_) => std::u64::MAX,
+        }
+    }
+
+    /// Returns -1, 0, or 1 depending on the sign of `self`.
+    pub fn signum(&self) -> i32 {
+        match self.0.sign() {
+            BigIntSign::Minus => -1,
+            BigIntSign::NoSign => 0,
+            BigIntSign::Plus => 1,
+        }
+    }
+
+    pub fn checked_add(self, other: BigInt) -> Result<BigInt, ArithmeticError> {
+        Ok(BigInt(self.0.add(other.0)))
+    }
+
+    pub fn checked_sub(self, other: BigInt) -> Result<BigInt, ArithmeticError> {
+        Ok(BigInt(self.0.sub(other.0)))
+    }
+
+    pub fn checked_mul(self, other: BigInt) -> Result<BigInt, ArithmeticError> {
+        Ok(BigInt(self.0.mul(other.0)))
+    }
+
+    /// Like `/`, but returns an error instead of panicking on a zero divisor.
+    pub fn checked_div(self, other: BigInt) -> Result<BigInt, ArithmeticError> {
+        if other.is_zero() {
+            return Err(ArithmeticError::DivisionByZero);
+        }
+        Ok(BigInt(self.0.div(other.0)))
+    }
+
+    /// Like `%`, but returns an error instead of panicking on a zero divisor.
+    pub fn checked_rem(self, other: BigInt) -> Result<BigInt, ArithmeticError> {
+        if other.is_zero() {
+            return Err(ArithmeticError::DivisionByZero);
+        }
+        Ok(BigInt(self.0.rem(other.0)))
+    }
 }
 
 impl Display for BigInt {
@@ -199,6 +726,31 @@ impl From<i32> for BigInt {
     }
 }
 
+impl From<u8> for BigInt {
+    fn from(i: u8) -> BigInt {
+        BigInt(i.into())
+    }
+}
+
+impl From<u16> for BigInt {
+    fn from(i: u16) -> BigInt {
+        BigInt(i.into())
+    }
+}
+
+impl From<u32> for BigInt {
+    fn from(i: u32) -> BigInt {
+        BigInt(i.into())
+    }
+}
+
+/// `true` is 1 and `false` is 0
+impl From<bool> for BigInt {
+    fn from(b: bool) -> BigInt {
+        BigInt((b as u8).into())
+    }
+}
+
 impl From<u64> for BigInt {
     fn from(i: u64) -> BigInt {
         BigInt(i.into())
@@ -211,6 +763,18 @@ impl From<i64> for BigInt {
     }
 }
 
+impl From<i128> for BigInt {
+    fn from(i: i128) -> BigInt {
+        BigInt(i.into())
+    }
+}
+
+impl From<u128> for BigInt {
+    fn from(i: u128) -> BigInt {
+        BigInt(i.into())
+    }
+}
+
 impl From<U64> for BigInt {
     /// This implementation assumes that U64 represents an unsigned U64,
     /// and not a signed U64 (aka int64 in Solidity). Right now, this is
@@ -224,10 +788,8 @@ impl From<U64> for BigInt {
 
 impl From<U128> for BigInt {
     /// This implementation assumes that U128 represents an unsigned U128,
-    /// and not a signed U128 (aka int128 in Solidity). Right now, this is
-    /// all we need (for block numbers). If it ever becomes necessary to
-    /// handle signed U128s, we should add the same
-    /// `{to,from}_{signed,unsigned}_u128` methods that we have for U256.
+    /// and not a signed U128 (aka int128 in Solidity). Use
+    /// `BigInt::from_signed_u128` for signed values.
     fn from(n: U128) -> BigInt {
         let mut bytes: [u8; 16] = [0; 16];
         n.to_little_endian(&mut bytes);
@@ -258,11 +820,58 @@ impl<'de> Deserialize<'de> for BigInt {
     }
 }
 
+impl Neg for BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt(self.0.neg())
+    }
+}
+
+/// Serde helpers to serialize a `BigInt` as a JSON number if it fits into an
+/// `i64` or `u64` and as a decimal string otherwise. Deserialization accepts
+/// both numbers and strings. Use as `#[serde(with = "big_int_as_number")]` on
+/// a field.
+pub mod big_int_as_number {
+    use super::BigInt;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::convert::TryFrom;
+    use std::str::FromStr;
+
+    pub fn serialize<S: Serializer>(value: &BigInt, serializer: S) -> Result<S::Ok, S::Error> {
+        if let Ok(n) = i64::try_from(value) {
+            serializer.serialize_i64(n)
+        } else if let Ok(n) = u64::try_from(value) {
+            serializer.serialize_u64(n)
+        } else {
+            serializer.serialize_str(&value.to_string())
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BigInt, D::Error> {
+        use serde::de::Error;
+
+        // Going through `serde_json::Value` preserves the exact digits of
+        // numbers that are too large for `i64` and `u64`
+        let decimal_string = match serde_json::Value::deserialize(deserializer)? {
+            serde_json::Value::Number(n) => n.to_string(),
+            serde_json::Value::String(s) => s,
+            value => {
+                return Err(D::Error::custom(format!(
+                    "expected a number or a decimal string, got `{}`",
+                    value
+                )))
+            }
+        };
+        BigInt::from_str(&decimal_string).map_err(D::Error::custom)
+    }
+}
+
 impl Add for BigInt {
     type Output = BigInt;
 
     fn add(self, other: BigInt) -> BigInt {
-        BigInt(self.0.add(other.0))
+        self.checked_add(other).unwrap()
     }
 }
 
@@ -270,7 +879,7 @@ impl Sub for BigInt {
     type Output = BigInt;
 
     fn sub(self, other: BigInt) -> BigInt {
-        BigInt(self.0.sub(other.0))
+        self.checked_sub(other).unwrap()
     }
 }
 
@@ -278,7 +887,7 @@ impl Mul for BigInt {
     type Output = BigInt;
 
     fn mul(self, other: BigInt) -> BigInt {
-        BigInt(self.0.mul(other.0))
+        self.checked_mul(other).unwrap()
     }
 }
 
@@ -286,11 +895,8 @@ impl Div for BigInt {
     type Output = BigInt;
 
     fn div(self, other: BigInt) -> BigInt {
-        if other == BigInt::from(0) {
-            panic!("Cannot divide by zero-valued `BigInt`!")
-        }
-
-        BigInt(self.0.div(other.0))
+        self.checked_div(other)
+            .expect("Cannot divide by zero-valued `BigInt`!")
     }
 }
 
@@ -298,128 +904,2856 @@ impl Rem for BigInt {
     type Output = BigInt;
 
     fn rem(self, other: BigInt) -> BigInt {
-        BigInt(self.0.rem(other.0))
+        self.checked_rem(other)
+            .expect("Cannot divide by zero-valued `BigInt`!")
     }
 }
 
-/// A byte array that's serialized as a hex string prefixed by `0x`.
-#[derive(Clone, Debug, PartialEq, Eq)]
-pub struct Bytes(Box<[u8]>);
+/// Bitwise operations on `BigInt` treat negative values as if they were
+/// represented in two's complement with infinite sign extension, which is
+/// consistent with `to_signed_bytes_le`.
+impl BitAnd for BigInt {
+    type Output = BigInt;
 
-impl StableHash for Bytes {
-    fn stable_hash(&self, sequence_number: impl SequenceNumber, state: &mut impl StableHasher) {
-        AsBytes(&self.0).stable_hash(sequence_number, state);
+    fn bitand(self, other: BigInt) -> BigInt {
+        BigInt(self.0.bitand(other.0))
     }
 }
 
-impl Bytes {
-    pub fn as_slice(&self) -> &[u8] {
-        &self.0
+impl BitOr for BigInt {
+    type Output = BigInt;
+
+    fn bitor(self, other: BigInt) -> BigInt {
+        BigInt(self.0.bitor(other.0))
     }
 }
 
-impl Display for Bytes {
-    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
-        write!(f, "0x{}", hex::encode(&self.0))
+impl BitXor for BigInt {
+    type Output = BigInt;
+
+    fn bitxor(self, other: BigInt) -> BigInt {
+        BigInt(self.0.bitxor(other.0))
     }
 }
 
-impl FromStr for Bytes {
-    type Err = hex::FromHexError;
+impl Shl<u32> for BigInt {
+    type Output = BigInt;
 
-    fn from_str(s: &str) -> Result<Bytes, Self::Err> {
-        hex::decode(s.trim_start_matches("0x")).map(|x| Bytes(x.into()))
+    fn shl(self, bits: u32) -> BigInt {
+        BigInt(self.0.shl(bits as usize))
     }
 }
 
-impl<'a> From<&'a [u8]> for Bytes {
-    fn from(array: &[u8]) -> Self {
-        Bytes(array.into())
+/// This is an arithmetic shift, i.e., negative values are sign-extended and
+/// the result is rounded towards negative infinity.
+impl Shr<u32> for BigInt {
+    type Output = BigInt;
+
+    fn shr(self, bits: u32) -> BigInt {
+        BigInt(self.0.shr(bits as usize))
     }
 }
 
-impl From<Address> for Bytes {
-    fn from(address: Address) -> Bytes {
-        Bytes::from(address.as_ref())
+impl AddAssign for BigInt {
+    fn add_assign(&mut self, other: BigInt) {
+        self.0.add_assign(other.0)
     }
 }
 
-impl Serialize for Bytes {
-    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        self.to_string().serialize(serializer)
+impl SubAssign for BigInt {
+    fn sub_assign(&mut self, other: BigInt) {
+        self.0.sub_assign(other.0)
     }
 }
 
-impl<'de> Deserialize<'de> for Bytes {
-    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
-        use serde::de::Error;
-
-        let hex_string = <String>::deserialize(deserializer)?;
-        Bytes::from_str(&hex_string).map_err(D::Error::custom)
+impl MulAssign for BigInt {
+    fn mul_assign(&mut self, other: BigInt) {
+        self.0.mul_assign(other.0)
     }
 }
 
-#[cfg(test)]
-mod test {
-    use super::{big_decimal_stable_hash, BigDecimal, BigInt};
-    use stable_hash::prelude::*;
-    use stable_hash::utils::stable_hash_with_hasher;
-    use std::str::FromStr;
-    use twox_hash::XxHash64;
-    use web3::types::U64;
-
-    #[test]
-    fn bigint_to_from_u64() {
-        for n in 0..100 {
-            let u = U64::from(n as u64);
-            let bn = BigInt::from(u);
-            assert_eq!(n, bn.to_u64());
+impl DivAssign for BigInt {
+    fn div_assign(&mut self, other: BigInt) {
+        if other.is_zero() {
+            panic!("Cannot divide by zero-valued `BigInt`!")
         }
-    }
 
-    fn xx_stable_hash(value: impl StableHash) -> u64 {
-        stable_hash_with_hasher::<XxHash64, _>(&value)
+        self.0.div_assign(other.0)
     }
+}
 
-    fn same_stable_hash(left: impl StableHash, right: impl StableHash) {
-        let left = xx_stable_hash(left);
-        let right = xx_stable_hash(right);
-        assert_eq!(left, right);
+impl RemAssign for BigInt {
+    fn rem_assign(&mut self, other: BigInt) {
+        if other.is_zero() {
+            panic!("Cannot divide by zero-valued `BigInt`!")
+        }
+
+        self.0.rem_assign(other.0)
+    }
+}
+
+/// How to round a `BigDecimal` when digits have to be dropped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RoundingMode {
+    /// Round to the nearest neighbor; ties are rounded away from zero.
+    HalfUp,
+    /// Round to the nearest neighbor; ties are rounded to the even neighbor.
+    HalfEven,
+    /// Round towards negative infinity.
+    Floor,
+    /// Round towards positive infinity.
+    Ceil,
+}
+
+/// The largest exponent for which `ten_pow` uses a precomputed value. This
+/// covers the number of decimals of all common tokens.
+const TEN_POWS_MAX_EXP: u64 = 36;
+
+lazy_static! {
+    static ref TEN_POWS: Vec<num_bigint::BigInt> = (0..=TEN_POWS_MAX_EXP)
+        .map(|exp| num_traits::pow(num_bigint::BigInt::from(10), exp as usize))
+        .collect();
+}
+
+/// Returns `10^exp`.
+fn ten_pow(exp: u64) -> num_bigint::BigInt {
+    if exp <= TEN_POWS_MAX_EXP {
+        TEN_POWS[exp as usize].clone()
+    } else {
+        num_traits::pow(num_bigint::BigInt::from(10), exp as usize)
+    }
+}
+
+/// The number of digits that are dropped from `digits` when its scale goes
+/// from `current_scale` down to `scale`. Returns `None` if that drops all
+/// digits of `digits` and at least one more. The divisor `10^dropped` would
+/// then be pointlessly expensive to compute, and the number of dropped digits
+/// might not even fit into an `i64`.
+fn dropped_digits(digits: &num_bigint::BigInt, current_scale: i64, scale: i64) -> Option<u64> {
+    let dropped = current_scale.checked_sub(scale)? as u64;
+    if dropped > digits.magnitude().to_string().len() as u64 {
+        None
+    } else {
+        Some(dropped)
+    }
+}
+
+impl BigDecimal {
+    /// The number of significant digits in the result of a division.
+    /// Quotients that do not terminate earlier, like `1/3`, are rounded to
+    /// this many digits. This is the precision that divisions in the
+    /// `bigdecimal` crate have always used; changing it changes the result of
+    /// divisions in mappings, and therefore the data subgraphs produce.
+    pub const DIVISION_PRECISION: u64 = 100;
+
+    /// Creates a `BigDecimal` with the value `digits * 10^-scale`.
+    pub fn new(digits: num_bigint::BigInt, scale: i64) -> Self {
+        BigDecimal(bigdecimal::BigDecimal::new(digits, scale))
+    }
+
+    /// Converts `n` to a `BigDecimal`, or returns an error if `n` is `NaN`
+    /// or infinite.
+    pub fn try_from_f64(n: f64) -> Result<BigDecimal, ArithmeticError> {
+        if n.is_finite() {
+            Ok(BigDecimal::from(n))
+        } else {
+            Err(ArithmeticError::NotFinite)
+        }
+    }
+
+    /// Converts the raw amount of a token with `decimals` decimals, e.g. a
+    /// balance from an ERC20 contract, into the amount of whole tokens,
+    /// i.e. `raw / 10^decimals`. The result is exact since this only changes
+    /// the scale.
+    pub fn from_raw_token_amount(raw: &BigInt, decimals: u8) -> BigDecimal {
+        raw.clone().to_big_decimal(BigInt::from(-(decimals as i32)))
+    }
+
+    /// The inverse of `from_raw_token_amount`: converts `self` into a raw
+    /// fixed-point amount with `decimals` decimals, i.e. `self * 10^decimals`.
+    /// Returns a `PrecisionLoss` error if `self` has more than `decimals`
+    /// digits after the decimal point that are not zero; use `truncate` or
+    /// `with_scale_round` first to drop them deliberately.
+    pub fn to_raw_fixed_point(&self, decimals: u8) -> Result<BigInt, ArithmeticError> {
+        let (digits, scale) = self.as_bigint_and_exponent();
+        let scale = scale - decimals as i64;
+        if scale <= 0 {
+            return Ok(BigInt(digits * ten_pow(-scale as u64)));
+        }
+        let (raw, remainder) = digits.div_rem(&ten_pow(scale as u64));
+        if remainder.is_zero() {
+            Ok(BigInt(raw))
+        } else {
+            Err(ArithmeticError::PrecisionLoss)
+        }
+    }
+
+    /// Converts a JSON number or a string holding a decimal number into a
+    /// `BigDecimal`
+    pub fn from_json_value(value: &serde_json::Value) -> Result<BigDecimal, InvalidJsonNumber> {
+        match value {
+            serde_json::Value::Number(n) => BigDecimal::from_str(&n.to_string()).ok(),
+            serde_json::Value::String(s) => BigDecimal::from_str(s).ok(),
+            _ => None,
+        }
+        .ok_or_else(|| InvalidJsonNumber(value.to_string()))
+    }
+
+    /// Converts `self` into a JSON string, like `BigInt::to_json_value`
+    pub fn to_json_value(&self) -> serde_json::Value {
+        serde_json::Value::String(self.to_string())
+    }
+
+    /// Creates a `BigDecimal` from the little-endian bytes of its digits and
+    /// its scale, the inverse of `as_bigint_and_exponent`. If `signed` is
+    /// true, `unscaled` is interpreted as a two's complement number.
+    pub fn from_bytes_le(unscaled: &[u8], signed: bool, exp: i64) -> BigDecimal {
+        let digits = if signed {
+            num_bigint::BigInt::from_signed_bytes_le(unscaled)
+        } else {
+            num_bigint::BigInt::from_bytes_le(BigIntSign::Plus, unscaled)
+        };
+        BigDecimal::new(digits, exp)
+    }
+
+    /// Returns the digits and the scale; note that a positive scale
+    /// indicates a negative power of 10.
+    pub fn as_bigint_and_exponent(&self) -> (num_bigint::BigInt, i64) {
+        self.0.as_bigint_and_exponent()
+    }
+
+    /// Shows the digits and the scale of `self`, e.g. `BigDecimal {
+    /// unscaled: 1500, scale: 3 }` for `1.500`. `Display` and `Debug` only
+    /// show the value, which hides the difference between values like `1.5`
+    /// and `1.500`; this is meant for troubleshooting such differences.
+    pub fn debug_repr(&self) -> String {
+        let (unscaled, scale) = self.as_bigint_and_exponent();
+        format!("BigDecimal {{ unscaled: {}, scale: {} }}", unscaled, scale)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+
+    /// Whether `self` has no fractional part. Since values are not
+    /// normalized implicitly, this also holds for values like `4.00` with
+    /// only zeros after the decimal point.
+    pub fn is_integer(&self) -> bool {
+        let (digits, scale) = self.as_bigint_and_exponent();
+        scale <= 0 || (digits % ten_pow(scale as u64)).is_zero()
+    }
+
+    /// Returns the absolute value, with the same scale as `self`.
+    pub fn abs(&self) -> BigDecimal {
+        use num_traits::Signed;
+
+        let (digits, scale) = self.as_bigint_and_exponent();
+        BigDecimal::new(digits.abs(), scale)
+    }
+
+    /// Returns -1, 0, or 1 depending on the sign of `self`.
+    pub fn signum(&self) -> i32 {
+        match self.0.sign() {
+            BigIntSign::Minus => -1,
+            BigIntSign::NoSign => 0,
+            BigIntSign::Plus => 1,
+        }
+    }
+
+    pub fn min(self, other: BigDecimal) -> BigDecimal {
+        std::cmp::min(self, other)
+    }
+
+    pub fn max(self, other: BigDecimal) -> BigDecimal {
+        std::cmp::max(self, other)
+    }
+
+    /// Restricts `self` to the interval `[lo, hi]`. Panics if `lo > hi`.
+    pub fn clamp(self, lo: BigDecimal, hi: BigDecimal) -> BigDecimal {
+        assert!(lo <= hi, "clamp: lo `{}` is greater than hi `{}`", lo, hi);
+        self.max(lo).min(hi)
+    }
+
+    /// Compares the numeric values of `self` and `other` regardless of their
+    /// scale, so that `1.0` and `1` are equal. `Ord` and `Eq` already ignore
+    /// the scale, and agree with `cmp_value` and `eq_value`; these methods
+    /// make that explicit where values come from sources that do not
+    /// normalize them.
+    pub fn cmp_value(&self, other: &BigDecimal) -> std::cmp::Ordering {
+        let (digits, other_digits, _) = self.aligned_digits(other);
+        digits.cmp(&other_digits)
+    }
+
+    /// Whether `self` and `other` have the same numeric value, regardless of
+    /// their scale. See `cmp_value`.
+    pub fn eq_value(&self, other: &BigDecimal) -> bool {
+        self.cmp_value(other) == std::cmp::Ordering::Equal
+    }
+
+    /// Returns the number of decimal digits.
+    pub fn digits(&self) -> u64 {
+        self.0.digits()
+    }
+
+    /// Formats the value in scientific notation, with exactly one nonzero
+    /// digit before the decimal point and without trailing zeros, for
+    /// example `1.23e-9` or `-1e10`. Zero is formatted as `0e0`. The result
+    /// can be parsed with `from_str`, which accepts both `e` and `E` as the
+    /// exponent marker.
+    pub fn to_scientific_string(&self) -> String {
+        if self.is_zero() {
+            return "0e0".to_owned();
+        }
+
+        let (digits, scale) = self.as_bigint_and_exponent();
+        let sign = if digits.sign() == BigIntSign::Minus {
+            "-"
+        } else {
+            ""
+        };
+        let digits = digits.magnitude().to_string();
+        let significant = digits.trim_end_matches('0');
+        let scale = scale - (digits.len() - significant.len()) as i64;
+        let exponent = significant.len() as i64 - 1 - scale;
+
+        let (first, rest) = significant.split_at(1);
+        if rest.is_empty() {
+            format!("{}{}e{}", sign, first, exponent)
+        } else {
+            format!("{}{}.{}e{}", sign, first, rest, exponent)
+        }
+    }
+
+    /// Formats the value in plain decimal notation, without an exponent and
+    /// without trailing zeros after the decimal point, for example `1900`,
+    /// `0.1` or `-0.1`. Zero is formatted as `0`. Values that are equal have
+    /// the same canonical string, regardless of their scale, which makes it
+    /// suitable for storing and comparing values as text.
+    pub fn to_canonical_string(&self) -> String {
+        if self.is_zero() {
+            return "0".to_owned();
+        }
+
+        let (digits, scale) = self.as_bigint_and_exponent();
+        let sign = if digits.sign() == BigIntSign::Minus {
+            "-"
+        } else {
+            ""
+        };
+        let digits = digits.magnitude().to_string();
+        let significant = digits.trim_end_matches('0');
+        let scale = scale - (digits.len() - significant.len()) as i64;
+
+        if scale <= 0 {
+            let zeros = "0".repeat(-scale as usize);
+            format!("{}{}{}", sign, significant, zeros)
+        } else if (scale as usize) < significant.len() {
+            let (int, frac) = significant.split_at(significant.len() - scale as usize);
+            format!("{}{}.{}", sign, int, frac)
+        } else {
+            let zeros = "0".repeat(scale as usize - significant.len());
+            format!("{}0.{}{}", sign, zeros, significant)
+        }
+    }
+
+    /// Returns the same value with exactly `scale` digits after the decimal
+    /// point. Increasing the scale pads with zeros, so `1.5` with scale 4 is
+    /// `1.5000`. Decreasing the scale drops digits without rounding, which
+    /// truncates towards zero; use `with_scale_round` to round instead.
+    ///
+    /// `BigDecimal` values are never normalized implicitly, so the scale is
+    /// kept until arithmetic produces a new value. Since equality and hashing
+    /// ignore the scale, `1.5` and `1.5000` still compare equal.
+    pub fn with_scale(&self, scale: i64) -> BigDecimal {
+        BigDecimal(self.0.with_scale(scale))
+    }
+
+    /// Returns the value rounded to `scale` digits after the decimal point
+    /// according to `mode`. The result keeps exactly `scale` digits after
+    /// the decimal point and is not normalized, so `2.3` rounded to scale 2
+    /// is `2.30`, like for `with_scale`.
+    pub fn with_scale_round(&self, scale: i64, mode: RoundingMode) -> BigDecimal {
+        let (digits, current_scale) = self.as_bigint_and_exponent();
+        if current_scale <= scale {
+            // No digits are dropped, no rounding required
+            return self.with_scale(scale);
+        }
+
+        // The quotient is truncated towards zero and the remainder has the
+        // same sign as `digits`. When all digits are dropped, the remainder
+        // is less than half of the divisor
+        let (quotient, remainder, half) = match dropped_digits(&digits, current_scale, scale) {
+            Some(dropped) => {
+                use num_traits::Signed;
+
+                let divisor = ten_pow(dropped);
+                let (quotient, remainder) = digits.div_rem(&divisor);
+                let half = (remainder.abs() * 2).cmp(&divisor);
+                (quotient, remainder, half)
+            }
+            None => (
+                num_bigint::BigInt::from(0),
+                digits.clone(),
+                std::cmp::Ordering::Less,
+            ),
+        };
+        let away_from_zero = match mode {
+            RoundingMode::Floor => remainder.sign() == BigIntSign::Minus,
+            RoundingMode::Ceil => remainder.sign() == BigIntSign::Plus,
+            RoundingMode::HalfUp | RoundingMode::HalfEven => match half {
+                std::cmp::Ordering::Less => false,
+                std::cmp::Ordering::Greater => true,
+                std::cmp::Ordering::Equal => mode == RoundingMode::HalfUp || quotient.is_odd(),
+            },
+        };
+        let quotient = if !away_from_zero {
+            quotient
+        } else if digits.sign() == BigIntSign::Minus {
+            quotient - 1
+        } else {
+            quotient + 1
+        };
+        BigDecimal::new(quotient, scale)
+    }
+
+    /// Drops all digits beyond `scale` digits after the decimal point, which
+    /// rounds towards zero. If digits are dropped, the result keeps exactly
+    /// `scale` digits after the decimal point; otherwise, the value is
+    /// returned unchanged and is not padded with zeros.
+    pub fn truncate(&self, scale: i64) -> BigDecimal {
+        let (digits, current_scale) = self.as_bigint_and_exponent();
+        if current_scale <= scale {
+            return self.clone();
+        }
+        match dropped_digits(&digits, current_scale, scale) {
+            Some(dropped) => BigDecimal::new(digits / ten_pow(dropped), scale),
+            None => BigDecimal::new(num_bigint::BigInt::from(0), scale),
+        }
+    }
+
+    /// Like `/`, but returns an error instead of panicking on a zero divisor
+    /// or if the scale of the quotient does not fit into an `i64`. The
+    /// quotient has `DIVISION_PRECISION` significant digits.
+    pub fn checked_div(&self, other: &BigDecimal) -> Result<BigDecimal, ArithmeticError> {
+        self.try_div_with_precision(other, Self::DIVISION_PRECISION)
+    }
+
+    /// Divides `self` by `other`, rounding the quotient to `digits`
+    /// significant digits; quotients that terminate earlier are exact, so
+    /// that `1/8` is `0.125`. If the integer part of the quotient has more
+    /// than `digits` digits, the quotient is rounded to an integer instead.
+    /// Panics if `other` is zero or the scale of the quotient does not fit
+    /// into an `i64`; see `checked_div`.
+    ///
+    /// The quotient is computed with integer arithmetic only, with the same
+    /// algorithm and rounding, the next digit rounding half up, as the
+    /// division of the `bigdecimal` crate uses with its fixed precision of
+    /// 100 digits. The result does therefore not depend on the version of
+    /// that crate.
+    pub fn div_with_precision(&self, other: &BigDecimal, digits: u64) -> BigDecimal {
+        match self.try_div_with_precision(other, digits) {
+            Ok(quotient) => quotient,
+            Err(ArithmeticError::DivisionByZero) => {
+                panic!("Cannot divide by zero-valued `BigDecimal`!")
+            }
+            Err(e) => panic!("Cannot divide `{}` by `{}`: {}", self, other, e),
+        }
+    }
+
+    fn try_div_with_precision(
+        &self,
+        other: &BigDecimal,
+        digits: u64,
+    ) -> Result<BigDecimal, ArithmeticError> {
+        use num_traits::Signed;
+
+        if other.is_zero() {
+            return Err(ArithmeticError::DivisionByZero);
+        }
+        if self.is_zero() || other.0.is_one() {
+            return Ok(self.clone());
+        }
+
+        let (a, a_scale) = self.as_bigint_and_exponent();
+        let (b, b_scale) = other.as_bigint_and_exponent();
+        let scale = a_scale
+            .checked_sub(b_scale)
+            .ok_or(ArithmeticError::ExponentOutOfRange)?;
+        if a == b {
+            return Ok(BigDecimal::new(num_bigint::BigInt::one(), scale));
+        }
+
+        let negative = a.is_negative() != b.is_negative();
+        let mut num = a.abs();
+        let den = b.abs();
+
+        // The number of digits by which the quotient is shifted to the
+        // left. It is bounded by the number of digits of `den` and by
+        // `digits`, not by the scales of the operands
+        let mut shift: u64 = 0;
+
+        // Shift `num` until it is at least as large as `den`, so that the
+        // first quotient has at least one digit
+        let ten = num_bigint::BigInt::from(10);
+        while num < den {
+            num *= &ten;
+            shift += 1;
+        }
+
+        let (mut quotient, mut remainder) = num.div_rem(&den);
+        if !remainder.is_zero() {
+            // Compute one more digit of the quotient for each step until
+            // the division terminates or we have `digits` digits
+            let mut precision = quotient.to_string().len() as u64;
+            remainder *= &ten;
+            while !remainder.is_zero() && precision < digits {
+                let (q, r) = remainder.div_rem(&den);
+                quotient = quotient * &ten + q;
+                remainder = r * &ten;
+                precision += 1;
+                shift += 1;
+            }
+
+            // Round with the next digit
+            if !remainder.is_zero() && &remainder / &den >= num_bigint::BigInt::from(5) {
+                quotient += 1u8;
+            }
+        }
+
+        let scale = i64::try_from(shift)
+            .ok()
+            .and_then(|shift| scale.checked_add(shift))
+            .ok_or(ArithmeticError::ExponentOutOfRange)?;
+        let quotient = if negative { -quotient } else { quotient };
+        Ok(BigDecimal::new(quotient, scale))
+    }
+
+    /// Like `%`, but returns an error instead of panicking on a zero divisor.
+    /// As for Rust's primitive types, the remainder has the sign of `self`,
+    /// so that `self == (self / other).truncate(0) * other + self % other`.
+    pub fn checked_rem(&self, other: &BigDecimal) -> Result<BigDecimal, ArithmeticError> {
+        if other.is_zero() {
+            return Err(ArithmeticError::DivisionByZero);
+        }
+        // With both values at the same scale, the remainder of their digits
+        // is exact
+        let (digits, other_digits, scale) = self.aligned_digits(other);
+        Ok(BigDecimal::new(digits % other_digits, scale))
+    }
+
+    /// Returns the digits of `self` and `other` at the larger of their two
+    /// scales, together with that scale.
+    fn aligned_digits(&self, other: &BigDecimal) -> (num_bigint::BigInt, num_bigint::BigInt, i64) {
+        let (digits, scale) = self.as_bigint_and_exponent();
+        let (other_digits, other_scale) = other.as_bigint_and_exponent();
+        let common_scale = scale.max(other_scale);
+        (
+            digits * ten_pow((common_scale - scale) as u64),
+            other_digits * ten_pow((common_scale - other_scale) as u64),
+            common_scale,
+        )
+    }
+
+    /// Returns what percentage `self` is of `whole`, i.e. `self / whole * 100`.
+    /// Returns zero if `whole` is zero, since mappings computing shares of
+    /// an empty total would otherwise have to special-case that everywhere.
+    pub fn percent_of(&self, whole: &BigDecimal) -> BigDecimal {
+        self.ratio_of(whole, 100)
+    }
+
+    /// Like `percent_of`, but in basis points, i.e. `self / whole * 10000`.
+    /// Returns zero if `whole` is zero.
+    pub fn basis_points_of(&self, whole: &BigDecimal) -> BigDecimal {
+        self.ratio_of(whole, 10_000)
+    }
+
+    fn ratio_of(&self, whole: &BigDecimal, factor: i64) -> BigDecimal {
+        // Multiply first so that the factor does not scale up the rounding
+        // error of the division
+        (self.clone() * BigDecimal::from(factor))
+            .checked_div(whole)
+            .unwrap_or_else(|_| BigDecimal::zero())
+    }
+
+    /// Returns the square root with `scale` digits after the decimal point,
+    /// truncating any further digits, or `None` if `self` is negative. The
+    /// computation only uses integer arithmetic and is therefore
+    /// deterministic across platforms.
+    pub fn sqrt(&self, scale: i64) -> Option<BigDecimal> {
+        if self.0.sign() == BigIntSign::Minus {
+            return None;
+        }
+
+        // With `self = digits * 10^-current_scale`, the result is
+        // `floor(sqrt(digits * 10^(2 * scale - current_scale))) * 10^-scale`
+        let (digits, current_scale) = self.as_bigint_and_exponent();
+        let shift = 2 * scale - current_scale;
+        let radicand = if shift >= 0 {
+            digits * ten_pow(shift as u64)
+        } else {
+            digits / ten_pow((-shift) as u64)
+        };
+        Some(BigDecimal::new(radicand.sqrt(), scale))
+    }
+
+    /// Returns the largest integer less than or equal to `self`.
+    pub fn floor(&self) -> BigInt {
+        BigInt(
+            self.with_scale_round(0, RoundingMode::Floor)
+                .as_bigint_and_exponent()
+                .0,
+        )
+    }
+
+    /// Returns the smallest integer greater than or equal to `self`.
+    pub fn ceil(&self) -> BigInt {
+        BigInt(
+            self.with_scale_round(0, RoundingMode::Ceil)
+                .as_bigint_and_exponent()
+                .0,
+        )
+    }
+}
+
+/// Equal `BigDecimal`s can have different scales, e.g., `1.0` and `1.00`,
+/// so we hash the digits and scale with all trailing zeros removed to stay
+/// consistent with `Eq`.
+impl Hash for BigDecimal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let (mut digits, mut scale) = self.0.as_bigint_and_exponent();
+        if digits.is_zero() {
+            scale = 0;
+        } else {
+            let ten = num_bigint::BigInt::from(10);
+            loop {
+                let (quotient, remainder) = digits.div_rem(&ten);
+                if !remainder.is_zero() {
+                    break;
+                }
+                digits = quotient;
+                scale -= 1;
+            }
+        }
+        digits.hash(state);
+        scale.hash(state);
+    }
+}
+
+impl Display for BigDecimal {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Debug for BigDecimal {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl FromStr for BigDecimal {
+    type Err = <bigdecimal::BigDecimal as FromStr>::Err;
+
+    fn from_str(s: &str) -> Result<BigDecimal, Self::Err> {
+        bigdecimal::BigDecimal::from_str(s).map(BigDecimal)
+    }
+}
+
+impl From<bigdecimal::BigDecimal> for BigDecimal {
+    fn from(big_decimal: bigdecimal::BigDecimal) -> BigDecimal {
+        BigDecimal(big_decimal)
+    }
+}
+
+impl From<BigDecimal> for bigdecimal::BigDecimal {
+    fn from(big_decimal: BigDecimal) -> bigdecimal::BigDecimal {
+        big_decimal.0
+    }
+}
+
+impl From<i32> for BigDecimal {
+    fn from(n: i32) -> BigDecimal {
+        BigDecimal(i64::from(n).into())
+    }
+}
+
+impl From<i64> for BigDecimal {
+    fn from(n: i64) -> BigDecimal {
+        BigDecimal(n.into())
+    }
+}
+
+impl From<u64> for BigDecimal {
+    fn from(n: u64) -> BigDecimal {
+        BigDecimal(n.into())
+    }
+}
+
+/// Do not use this with floats from untrusted sources since the conversion
+/// fails for `NaN` and infinite values; use `BigDecimal::try_from_f64`
+/// instead.
+impl From<f64> for BigDecimal {
+    fn from(n: f64) -> BigDecimal {
+        BigDecimal(n.into())
+    }
+}
+
+impl TryFrom<BigDecimal> for BigInt {
+    type Error = BigIntOutOfRangeError;
+
+    /// Succeeds only if `value` is an integer, i.e., if it has no nonzero
+    /// digits after the decimal point.
+    fn try_from(value: BigDecimal) -> Result<BigInt, BigIntOutOfRangeError> {
+        let (digits, scale) = value.as_bigint_and_exponent();
+        if scale <= 0 {
+            return Ok(BigInt(digits * ten_pow(-scale as u64)));
+        }
+        let (quotient, remainder) = digits.div_rem(&ten_pow(scale as u64));
+        if remainder.is_zero() {
+            Ok(BigInt(quotient))
+        } else {
+            Err(BigIntOutOfRangeError::Fractional)
+        }
+    }
+}
+
+impl Zero for BigDecimal {
+    fn zero() -> BigDecimal {
+        BigDecimal(bigdecimal::BigDecimal::zero())
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+}
+
+impl One for BigDecimal {
+    fn one() -> BigDecimal {
+        BigDecimal(bigdecimal::BigDecimal::one())
+    }
+}
+
+impl Serialize for BigDecimal {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for BigDecimal {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        bigdecimal::BigDecimal::deserialize(deserializer).map(BigDecimal)
+    }
+}
+
+impl ToSql<Numeric, Pg> for BigDecimal {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        <_ as ToSql<Numeric, Pg>>::to_sql(&self.0, out)
+    }
+}
+
+impl FromSql<Numeric, Pg> for BigDecimal {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        <bigdecimal::BigDecimal as FromSql<Numeric, Pg>>::from_sql(bytes).map(BigDecimal)
+    }
+}
+
+impl Add for BigDecimal {
+    type Output = BigDecimal;
+
+    fn add(self, other: BigDecimal) -> BigDecimal {
+        BigDecimal(self.0.add(other.0))
+    }
+}
+
+impl Sub for BigDecimal {
+    type Output = BigDecimal;
+
+    fn sub(self, other: BigDecimal) -> BigDecimal {
+        BigDecimal(self.0.sub(other.0))
+    }
+}
+
+impl Mul for BigDecimal {
+    type Output = BigDecimal;
+
+    fn mul(self, other: BigDecimal) -> BigDecimal {
+        BigDecimal(self.0.mul(other.0))
+    }
+}
+
+impl Div for BigDecimal {
+    type Output = BigDecimal;
+
+    fn div(self, other: BigDecimal) -> BigDecimal {
+        self.div_with_precision(&other, Self::DIVISION_PRECISION)
+    }
+}
+
+impl Rem for BigDecimal {
+    type Output = BigDecimal;
+
+    fn rem(self, other: BigDecimal) -> BigDecimal {
+        self.checked_rem(&other)
+            .expect("Cannot divide by zero-valued `BigDecimal`!")
+    }
+}
+
+impl AddAssign for BigDecimal {
+    fn add_assign(&mut self, other: BigDecimal) {
+        self.0 = &self.0 + &other.0;
+    }
+}
+
+impl SubAssign for BigDecimal {
+    fn sub_assign(&mut self, other: BigDecimal) {
+        self.0 = &self.0 - &other.0;
+    }
+}
+
+impl MulAssign for BigDecimal {
+    fn mul_assign(&mut self, other: BigDecimal) {
+        self.0 = &self.0 * &other.0;
+    }
+}
+
+impl DivAssign for BigDecimal {
+    fn div_assign(&mut self, other: BigDecimal) {
+        *self = self.div_with_precision(&other, Self::DIVISION_PRECISION);
+    }
+}
+
+impl RemAssign for BigDecimal {
+    fn rem_assign(&mut self, other: BigDecimal) {
+        *self = self
+            .checked_rem(&other)
+            .expect("Cannot divide by zero-valued `BigDecimal`!");
+    }
+}
+
+// The sum of no values is 0 and their product is 1
+macro_rules! impl_sum_product {
+    ($ty:ident) => {
+        impl Sum for $ty {
+            fn sum<I: Iterator<Item = $ty>>(iter: I) -> $ty {
+                iter.fold($ty::from(0), Add::add)
+            }
+        }
+
+        impl<'a> Sum<&'a $ty> for $ty {
+            fn sum<I: Iterator<Item = &'a $ty>>(iter: I) -> $ty {
+                iter.cloned().sum()
+            }
+        }
+
+        impl Product for $ty {
+            fn product<I: Iterator<Item = $ty>>(iter: I) -> $ty {
+                iter.fold($ty::from(1), Mul::mul)
+            }
+        }
+
+        impl<'a> Product<&'a $ty> for $ty {
+            fn product<I: Iterator<Item = &'a $ty>>(iter: I) -> $ty {
+                iter.cloned().product()
+            }
+        }
+    };
+}
+
+impl_sum_product!(BigInt);
+impl_sum_product!(BigDecimal);
+
+/// A byte array that's serialized as a hex string prefixed by `0x`. Values
+/// are ordered lexicographically by their bytes.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Bytes(Box<[u8]>);
+
+impl StableHash for Bytes {
+    fn stable_hash(&self, sequence_number: impl SequenceNumber, state: &mut impl StableHasher) {
+        AsBytes(&self.0).stable_hash(sequence_number, state);
+    }
+}
+
+/// A list of `Bytes` whose `StableHash` includes the length of each element,
+/// so that lists that only differ in how the same bytes are split between
+/// their elements, like `[0x01, 0x0203]` and `[0x0102, 0x03]`, hash
+/// differently.
+pub struct BytesList<'a>(pub &'a [Bytes]);
+
+impl StableHash for BytesList<'_> {
+    fn stable_hash(&self, mut sequence_number: impl SequenceNumber, state: &mut impl StableHasher) {
+        for bytes in self.0 {
+            let mut element_sequence_number = sequence_number.next_child();
+            bytes
+                .len()
+                .stable_hash(element_sequence_number.next_child(), state);
+            bytes.stable_hash(element_sequence_number.next_child(), state);
+        }
+        self.0.len().stable_hash(sequence_number, state);
+    }
+}
+
+impl Bytes {
+    pub fn as_slice(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns a copy of the bytes in the range `start..end`. Panics if the
+    /// range is out of bounds, just like indexing into a slice does.
+    pub fn slice(&self, start: usize, end: usize) -> Bytes {
+        if start > end || end > self.len() {
+            panic!(
+                "range {}..{} is out of bounds for Bytes of length {}",
+                start,
+                end,
+                self.len()
+            );
+        }
+        Bytes::from(&self.0[start..end])
+    }
+
+    /// Compares `self` and `other` in constant time, i.e., in time that only
+    /// depends on the lengths of the two values but not on their contents.
+    /// Use this instead of `==` when comparing secrets.
+    pub fn ct_eq(&self, other: &Bytes) -> bool {
+        if self.len() != other.len() {
+            return false;
+        }
+        let diff = self
+            .0
+            .iter()
+            .zip(other.0.iter())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b));
+        diff == 0
+    }
+
+    /// Encodes the bytes with the standard base64 alphabet, with padding.
+    pub fn to_base64(&self) -> String {
+        base64::encode(&self.0)
+    }
+
+    pub fn from_base64(s: &str) -> Result<Bytes, base64::DecodeError> {
+        base64::decode(s).map(|x| Bytes(x.into()))
+    }
+
+    /// Pads the value with zero bytes on the left to a total length of
+    /// `len`. Returns an error if the value is already longer than that.
+    pub fn left_pad(&self, len: usize) -> Result<Bytes, BytesTooLongError> {
+        let padding = self.padding(len)?;
+        let mut bytes = vec![0u8; padding];
+        bytes.extend_from_slice(&self.0);
+        Ok(Bytes(bytes.into()))
+    }
+
+    /// Pads the value with zero bytes on the right to a total length of
+    /// `len`. Returns an error if the value is already longer than that.
+    pub fn right_pad(&self, len: usize) -> Result<Bytes, BytesTooLongError> {
+        let padding = self.padding(len)?;
+        let mut bytes = self.0.to_vec();
+        bytes.resize(bytes.len() + padding, 0);
+        Ok(Bytes(bytes.into()))
+    }
+
+    fn padding(&self, len: usize) -> Result<usize, BytesTooLongError> {
+        len.checked_sub(self.len()).ok_or(BytesTooLongError {
+            len: self.len(),
+            target_len: len,
+        })
+    }
+
+    /// Returns a new `Bytes` with the contents of `self` followed by the
+    /// contents of `other`.
+    pub fn concat(&self, other: &Bytes) -> Bytes {
+        let mut bytes = Vec::with_capacity(self.len() + other.len());
+        bytes.extend_from_slice(&self.0);
+        bytes.extend_from_slice(&other.0);
+        Bytes(bytes.into())
+    }
+}
+
+impl Deref for Bytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for Bytes {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[derive(Fail, Debug, PartialEq)]
+#[fail(
+    display = "Bytes value of length {} does not fit into {} bytes",
+    len, target_len
+)]
+pub struct BytesTooLongError {
+    pub len: usize,
+    pub target_len: usize,
+}
+
+/// Serde helpers to (de)serialize `Bytes` as a base64 string instead of a
+/// hex string. Use as `#[serde(with = "bytes_as_base64")]` on a field.
+pub mod bytes_as_base64 {
+    use super::Bytes;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &Bytes, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&bytes.to_base64())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Bytes, D::Error> {
+        use serde::de::Error;
+
+        let base64_string = <String>::deserialize(deserializer)?;
+        Bytes::from_base64(&base64_string).map_err(D::Error::custom)
+    }
+}
+
+impl Add for Bytes {
+    type Output = Bytes;
+
+    fn add(self, other: Bytes) -> Bytes {
+        self.concat(&other)
+    }
+}
+
+impl Display for Bytes {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        write!(f, "0x{}", hex::encode(&self.0))
+    }
+}
+
+/// The error returned when a string can not be parsed into `Bytes`.
+///
+/// This implements `std::error::Error` rather than deriving `Fail` since it
+/// is passed to diesel, which expects standard errors.
+#[derive(Debug, PartialEq)]
+pub struct ParseBytesError {
+    pub input: String,
+    pub error: hex::FromHexError,
+}
+
+impl Display for ParseBytesError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        write!(
+            f,
+            "invalid Bytes value `{}`: {}; expected an even number of hex digits, \
+             optionally prefixed with `0x`",
+            self.input, self.error
+        )
+    }
+}
+
+impl std::error::Error for ParseBytesError {}
+
+impl FromStr for Bytes {
+    type Err = ParseBytesError;
+
+    /// Parses a hex string with an optional `0x` prefix. Hex digits may be
+    /// upper or lower case. An empty string, or just `0x`, results in empty
+    /// `Bytes`.
+    fn from_str(s: &str) -> Result<Bytes, Self::Err> {
+        hex::decode(s.trim_start_matches("0x"))
+            .map(|x| Bytes(x.into()))
+            .map_err(|error| ParseBytesError {
+                input: s.to_owned(),
+                error,
+            })
+    }
+}
+
+impl<'a> From<&'a [u8]> for Bytes {
+    fn from(array: &[u8]) -> Self {
+        Bytes(array.into())
+    }
+}
+
+impl From<Vec<u8>> for Bytes {
+    fn from(vec: Vec<u8>) -> Self {
+        Bytes(vec.into())
+    }
+}
+
+impl From<Address> for Bytes {
+    fn from(address: Address) -> Bytes {
+        Bytes::from(address.as_ref())
+    }
+}
+
+impl From<H256> for Bytes {
+    fn from(hash: H256) -> Bytes {
+        Bytes::from(hash.as_ref())
+    }
+}
+
+impl From<U256> for Bytes {
+    /// Converts `n` into its 32-byte big-endian representation, including
+    /// leading zero bytes.
+    fn from(n: U256) -> Bytes {
+        let mut bytes = [0u8; 32];
+        n.to_big_endian(&mut bytes);
+        Bytes::from(&bytes[..])
+    }
+}
+
+impl Serialize for Bytes {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_string().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Bytes {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let hex_string = <String>::deserialize(deserializer)?;
+        Bytes::from_str(&hex_string).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        big_decimal_stable_hash, ArithmeticError, BigDecimal, BigInt, BigIntOutOfRangeError,
+        BigIntSign, Bytes, BytesList, BytesTooLongError, InvalidJsonNumber, ParseBytesError,
+        RoundingMode,
+    };
+    use stable_hash::prelude::*;
+    use stable_hash::utils::stable_hash_with_hasher;
+    use std::collections::{BTreeSet, HashSet};
+    use std::convert::TryFrom;
+    use std::str::FromStr;
+    use twox_hash::XxHash64;
+    use web3::types::{H256, U128, U256, U64};
+
+    #[test]
+    fn bigint_to_from_u64() {
+        for n in 0..100 {
+            let u = U64::from(n as u64);
+            let bn = BigInt::from(u);
+            assert_eq!(n, bn.to_u64());
+        }
+    }
+
+    fn xx_stable_hash(value: impl StableHash) -> u64 {
+        stable_hash_with_hasher::<XxHash64, _>(&value)
+    }
+
+    fn same_stable_hash(left: impl StableHash, right: impl StableHash) {
+        let left = xx_stable_hash(left);
+        let right = xx_stable_hash(right);
+        assert_eq!(left, right);
+    }
+
+    struct BigDecimalStableHash(BigDecimal);
+    impl StableHash for BigDecimalStableHash {
+        fn stable_hash(&self, sequence_number: impl SequenceNumber, state: &mut impl StableHasher) {
+            big_decimal_stable_hash(&self.0, sequence_number, state);
+        }
+    }
+
+    struct VersionedBigInt(BigInt, u64);
+    impl StableHash for VersionedBigInt {
+        fn stable_hash(&self, sequence_number: impl SequenceNumber, state: &mut impl StableHasher) {
+            super::big_int_stable_hash_with_version(&(self.0).0, self.1, sequence_number, state);
+        }
+    }
+
+    struct VersionedBigDecimal(BigDecimal, u64);
+    impl StableHash for VersionedBigDecimal {
+        fn stable_hash(&self, sequence_number: impl SequenceNumber, state: &mut impl StableHasher) {
+            super::big_decimal_stable_hash_with_version(&self.0, self.1, sequence_number, state);
+        }
+    }
+
+    // Hashes its fields as consecutive children, the way version 1 of the
+    // big number layout does
+    struct Children<A, B, C>(A, B, Option<C>);
+    impl<A: StableHash, B: StableHash, C: StableHash> StableHash for Children<A, B, C> {
+        fn stable_hash(
+            &self,
+            mut sequence_number: impl SequenceNumber,
+            state: &mut impl StableHasher,
+        ) {
+            self.0.stable_hash(sequence_number.next_child(), state);
+            self.1.stable_hash(sequence_number.next_child(), state);
+            if let Some(c) = &self.2 {
+                c.stable_hash(sequence_number.next_child(), state);
+            }
+        }
+    }
+
+    #[test]
+    fn big_int_stable_hash_same_as_int() {
+        // In version 0 of the layout
+        let legacy = |n| VersionedBigInt(n, 0);
+        same_stable_hash(0, legacy(BigInt::from(0u64)));
+        same_stable_hash(1, legacy(BigInt::from(1u64)));
+        same_stable_hash(1u64 << 20, legacy(BigInt::from(1u64 << 20)));
+
+        same_stable_hash(
+            -1,
+            legacy(BigInt::from_signed_bytes_le(&(-1i32).to_le_bytes())),
+        );
+    }
+
+    #[test]
+    fn big_decimal_stable_hash_same_as_uint() {
+        // In version 0 of the layout
+        let legacy = |d| VersionedBigDecimal(d, 0);
+        same_stable_hash(0, legacy(BigDecimal::from(0u64)));
+        same_stable_hash(4, legacy(BigDecimal::from(4i64)));
+        same_stable_hash(1u64 << 21, legacy(BigDecimal::from(1u64 << 21)));
+    }
+
+    #[test]
+    fn big_number_stable_hash_version() {
+        // Changing the default changes proofs of indexing, see
+        // `BIG_NUMBER_STABLE_HASH_VERSION`
+        assert_eq!(0, super::BIG_NUMBER_STABLE_HASH_VERSION);
+        let n = BigInt::from(-1234);
+        same_stable_hash(n.clone(), VersionedBigInt(n.clone(), 0));
+        let d = BigDecimal::from_str("-0.1").unwrap();
+        same_stable_hash(
+            BigDecimalStableHash(d.clone()),
+            VersionedBigDecimal(d.clone(), 0),
+        );
+
+        // Version 1 hashes the version, then the value like the primitive
+        // integer types. `-0.1` is `-1` with exponent 1
+        same_stable_hash(
+            VersionedBigInt(n.clone(), 1),
+            Children(1u64, -1234, None::<u8>),
+        );
+        same_stable_hash(
+            VersionedBigDecimal(d.clone(), 1),
+            Children(1u64, 1, Some(-1)),
+        );
+
+        // Versions can not collide
+        assert_ne!(
+            xx_stable_hash(VersionedBigInt(n.clone(), 0)),
+            xx_stable_hash(VersionedBigInt(n.clone(), 1))
+        );
+        assert_ne!(
+            xx_stable_hash(VersionedBigInt(n.clone(), 1)),
+            xx_stable_hash(VersionedBigInt(n, 2))
+        );
+        assert_ne!(
+            xx_stable_hash(VersionedBigDecimal(d.clone(), 0)),
+            xx_stable_hash(VersionedBigDecimal(d, 1))
+        );
+    }
+
+    #[test]
+    fn big_decimal_stable() {
+        // Hashes with the default version 0 of the layout, see
+        // `BIG_NUMBER_STABLE_HASH_VERSION`. These must never change, since
+        // proofs of indexing depend on them
+        let cases = vec![(5580731626265347763, "0.1"), (15037326160029728810, "-0.1")];
+        for case in cases.iter() {
+            let dec = BigDecimal::from_str(case.1).unwrap();
+            assert_eq!(case.0, xx_stable_hash(BigDecimalStableHash(dec.clone())));
+            assert_eq!(case.0, xx_stable_hash(VersionedBigDecimal(dec, 0)));
+        }
+    }
+
+    #[test]
+    fn big_int_checked_arithmetic() {
+        assert_eq!(
+            BigInt::from(7).checked_add(BigInt::from(5)),
+            Ok(BigInt::from(12))
+        );
+        assert_eq!(
+            BigInt::from(7).checked_sub(BigInt::from(5)),
+            Ok(BigInt::from(2))
+        );
+        assert_eq!(
+            BigInt::from(7).checked_mul(BigInt::from(5)),
+            Ok(BigInt::from(35))
+        );
+        assert_eq!(
+            BigInt::from(7).checked_div(BigInt::from(5)),
+            Ok(BigInt::from(1))
+        );
+        assert_eq!(
+            BigInt::from(7).checked_rem(BigInt::from(5)),
+            Ok(BigInt::from(2))
+        );
+        assert_eq!(
+            BigInt::from(7).checked_div(BigInt::from(0)),
+            Err(ArithmeticError::DivisionByZero)
+        );
+        assert_eq!(
+            BigInt::from(7).checked_rem(BigInt::from(0)),
+            Err(ArithmeticError::DivisionByZero)
+        );
+    }
+
+    #[test]
+    fn big_int_bitwise_ops() {
+        assert_eq!(
+            BigInt::from(0b1100) & BigInt::from(0b1010),
+            BigInt::from(0b1000)
+        );
+        assert_eq!(
+            BigInt::from(0b1100) | BigInt::from(0b1010),
+            BigInt::from(0b1110)
+        );
+        assert_eq!(
+            BigInt::from(0b1100) ^ BigInt::from(0b1010),
+            BigInt::from(0b0110)
+        );
+
+        // Two's complement: -1 is all ones, -256 is all ones except the low byte
+        assert_eq!(BigInt::from(-1) & BigInt::from(0xff), BigInt::from(0xff));
+        assert_eq!(
+            BigInt::from(-256) & BigInt::from(0x1ff),
+            BigInt::from(0x100)
+        );
+        assert_eq!(BigInt::from(-256) | BigInt::from(0xff), BigInt::from(-1));
+        assert_eq!(BigInt::from(-1) ^ BigInt::from(0xff), BigInt::from(-256));
+    }
+
+    #[test]
+    fn big_int_shifts() {
+        assert_eq!(BigInt::from(1) << 8, BigInt::from(256));
+        assert_eq!(BigInt::from(-3) << 2, BigInt::from(-12));
+        assert_eq!(BigInt::from(256) >> 8, BigInt::from(1));
+        assert_eq!(BigInt::from(255) >> 8, BigInt::from(0));
+        assert_eq!(BigInt::from(-8) >> 1, BigInt::from(-4));
+        assert_eq!(BigInt::from(-1) >> 10, BigInt::from(-1));
+        assert_eq!(BigInt::from(-9) >> 1, BigInt::from(-5));
+    }
+
+    #[test]
+    fn big_int_sqrt() {
+        assert_eq!(BigInt::from(0).sqrt(), Ok(BigInt::from(0)));
+        assert_eq!(BigInt::from(1).sqrt(), Ok(BigInt::from(1)));
+        assert_eq!(BigInt::from(144).sqrt(), Ok(BigInt::from(12)));
+        assert_eq!(BigInt::from(143).sqrt(), Ok(BigInt::from(11)));
+
+        let max = BigInt::from(std::u64::MAX);
+        let square = max.clone() * max.clone();
+        assert_eq!(square.sqrt(), Ok(max.clone()));
+        assert_eq!((square + BigInt::from(1)).sqrt(), Ok(max.clone()));
+        let u128_max = BigInt::from_str(&std::u128::MAX.to_string()).unwrap();
+        assert_eq!(u128_max.sqrt(), Ok(max));
+
+        assert_eq!(
+            BigInt::from(-4).sqrt(),
+            Err(ArithmeticError::NegativeSquareRoot)
+        );
+    }
+
+    #[test]
+    fn big_int_gcd_lcm() {
+        assert_eq!(BigInt::from(12).gcd(&BigInt::from(18)), BigInt::from(6));
+        assert_eq!(BigInt::from(-12).gcd(&BigInt::from(-18)), BigInt::from(6));
+        assert_eq!(BigInt::from(-12).gcd(&BigInt::from(18)), BigInt::from(6));
+        assert_eq!(BigInt::from(7).gcd(&BigInt::from(0)), BigInt::from(7));
+
+        assert_eq!(BigInt::from(4).lcm(&BigInt::from(6)), BigInt::from(12));
+        assert_eq!(BigInt::from(-4).lcm(&BigInt::from(-6)), BigInt::from(12));
+        assert_eq!(BigInt::from(4).lcm(&BigInt::from(0)), BigInt::from(0));
+        assert_eq!(BigInt::from(0).lcm(&BigInt::from(0)), BigInt::from(0));
+    }
+
+    #[test]
+    fn big_int_modpow() {
+        let modulus = BigInt::from(1_000_000_007);
+        assert_eq!(
+            BigInt::from(2).modpow(&BigInt::from(10), &modulus),
+            Ok(BigInt::from(1024))
+        );
+
+        // 3 ^ 10^30 is far too large to compute with `pow`
+        let exponent = BigInt::from_str("1000000000000000000000000000000").unwrap();
+        assert_eq!(
+            BigInt::from(3).modpow(&exponent, &modulus),
+            Ok(BigInt::from(965115194))
+        );
+
+        // (-3)^3 = -27, which is 3 mod 5
+        assert_eq!(
+            BigInt::from(-3).modpow(&BigInt::from(3), &BigInt::from(5)),
+            Ok(BigInt::from(3))
+        );
+
+        assert_eq!(
+            BigInt::from(2).modpow(&BigInt::from(3), &BigInt::from(0)),
+            Err(ArithmeticError::DivisionByZero)
+        );
+        assert_eq!(
+            BigInt::from(2).modpow(&BigInt::from(-1), &modulus),
+            Err(ArithmeticError::NegativeExponent)
+        );
+    }
+
+    #[test]
+    fn big_int_neg_abs_signum() {
+        assert_eq!(-BigInt::from(5), BigInt::from(-5));
+        assert_eq!(-BigInt::from(-5), BigInt::from(5));
+        assert_eq!(-BigInt::from(0), BigInt::from(0));
+
+        assert_eq!(BigInt::from(-5).abs(), BigInt::from(5));
+        assert_eq!(BigInt::from(5).abs(), BigInt::from(5));
+        let min = BigInt::from(std::i64::MIN);
+        assert_eq!(min.abs(), BigInt::from(std::i64::MAX) + BigInt::from(1));
+
+        assert_eq!(BigInt::from(-5).signum(), -1);
+        assert_eq!(BigInt::from(0).signum(), 0);
+        assert_eq!(BigInt::from(5).signum(), 1);
+
+        for x in vec![0, 1, -1, 1 << 20, -(1 << 20)] {
+            let x = BigInt::from(x);
+            same_stable_hash(x.clone(), -(-x));
+        }
+    }
+
+    #[test]
+    fn big_int_try_into_small_ints() {
+        let min = BigInt::from(std::i32::MIN);
+        assert_eq!(i32::try_from(&min).unwrap(), std::i32::MIN);
+        assert_eq!(i64::try_from(&min).unwrap(), std::i32::MIN as i64);
+        assert!(matches!(
+            i32::try_from(min - BigInt::from(1)),
+            Err(BigIntOutOfRangeError::Overflow)
+        ));
+
+        let max = BigInt::from(std::i32::MAX);
+        assert_eq!(i32::try_from(&max).unwrap(), std::i32::MAX);
+        assert!(matches!(
+            i32::try_from(max + BigInt::from(1)),
+            Err(BigIntOutOfRangeError::Overflow)
+        ));
+
+        assert_eq!(u8::try_from(BigInt::from(255)).unwrap(), 255);
+        assert!(matches!(
+            u8::try_from(BigInt::from(256)),
+            Err(BigIntOutOfRangeError::Overflow)
+        ));
+        assert!(matches!(
+            u32::try_from(BigInt::from(-1)),
+            Err(BigIntOutOfRangeError::Negative)
+        ));
+        assert_eq!(u32::try_from(BigInt::from(0)).unwrap(), 0);
+        assert!(matches!(
+            u32::try_from(BigInt::from(std::u32::MAX as i64 + 1)),
+            Err(BigIntOutOfRangeError::Overflow)
+        ));
+        assert!(matches!(
+            i64::try_from(BigInt::from(std::u64::MAX)),
+            Err(BigIntOutOfRangeError::Overflow)
+        ));
+    }
+
+    #[test]
+    fn big_int_assign_ops() {
+        let values: Vec<BigInt> = (1..20).map(|i: i64| BigInt::from(i * 1_000_003)).collect();
+
+        let mut total = BigInt::from(0);
+        let mut expected = BigInt::from(0);
+        for value in &values {
+            total += value.clone();
+            expected = expected + value.clone();
+        }
+        assert_eq!(total, expected);
+
+        for value in &values {
+            total -= value.clone();
+            expected = expected - value.clone();
+        }
+        assert_eq!(total, BigInt::from(0));
+        assert_eq!(total, expected);
+
+        let mut product = BigInt::from(1);
+        for value in &values {
+            product *= value.clone();
+        }
+        for value in values.iter().rev() {
+            let mut remainder = product.clone();
+            remainder %= value.clone();
+            assert_eq!(remainder, BigInt::from(0));
+            product /= value.clone();
+        }
+        assert_eq!(product, BigInt::from(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot divide by zero-valued `BigInt`!")]
+    fn big_int_div_assign_by_zero() {
+        let mut x = BigInt::from(1);
+        x /= BigInt::from(0);
+    }
+
+    #[test]
+    fn big_decimal_assign_ops() {
+        let values: Vec<BigDecimal> = (1..20)
+            .map(|i| BigDecimal::from_str(&format!("{}.25", i)).unwrap())
+            .collect();
+
+        let mut total = BigDecimal::from(0);
+        let mut expected = BigDecimal::from(0);
+        for value in &values {
+            total += value.clone();
+            expected = expected + value.clone();
+        }
+        assert_eq!(total, expected);
+        assert_eq!(total, BigDecimal::from_str("194.75").unwrap());
+
+        total -= BigDecimal::from_str("0.75").unwrap();
+        total *= BigDecimal::from(2);
+        total /= BigDecimal::from(4);
+        assert_eq!(total, BigDecimal::from(97));
+    }
+
+    #[test]
+    fn big_decimal_hash_consistent_with_eq() {
+        let values = vec![
+            BigDecimal::from_str("1.5").unwrap(),
+            BigDecimal::from_str("1.50").unwrap(),
+            BigDecimal::from_str("1.500000").unwrap(),
+            BigDecimal::new(15.into(), 1),
+            BigDecimal::new(150.into(), 2),
+        ];
+        let set: HashSet<_> = values.into_iter().collect();
+        assert_eq!(set.len(), 1);
+
+        let values = vec![
+            BigDecimal::from(0),
+            BigDecimal::from_str("0.000").unwrap(),
+            BigDecimal::new(0.into(), -3),
+            BigDecimal::from(100),
+            BigDecimal::new(1.into(), -2),
+            BigDecimal::from_str("100.00").unwrap(),
+        ];
+        let set: HashSet<_> = values.into_iter().collect();
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn big_int_and_bytes_hash() {
+        let set: HashSet<_> = vec![BigInt::from(7), BigInt::from(7u64), BigInt::from(8)]
+            .into_iter()
+            .collect();
+        assert_eq!(set.len(), 2);
+
+        let set: HashSet<_> = vec![
+            Bytes::from_str("0xdead").unwrap(),
+            Bytes::from(&[0xde, 0xad][..]),
+            Bytes::from_str("0xbeef").unwrap(),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn big_int_from_str_radix() {
+        assert_eq!(BigInt::from_str_radix("ff", 16).unwrap(), BigInt::from(255));
+        assert_eq!(
+            BigInt::from_str_radix("0xff", 16).unwrap(),
+            BigInt::from(255)
+        );
+        assert_eq!(
+            BigInt::from_str_radix("-0xff", 16).unwrap(),
+            BigInt::from(-255)
+        );
+        assert_eq!(
+            BigInt::from_str_radix("0x10000000000000000", 16).unwrap(),
+            BigInt::from(std::u64::MAX) + BigInt::from(1)
+        );
+        assert_eq!(BigInt::from_str_radix("1011", 2).unwrap(), BigInt::from(11));
+        assert_eq!(BigInt::from_str_radix("-z", 36).unwrap(), BigInt::from(-35));
+
+        assert!(BigInt::from_str_radix("12", 2).is_err());
+        assert!(BigInt::from_str_radix("0xfg", 16).is_err());
+        assert!(BigInt::from_str_radix("0x10", 10).is_err());
+    }
+
+    #[test]
+    fn big_int_to_f64() {
+        assert_eq!(BigInt::from(0).to_f64(), Some(0.0));
+        assert_eq!(BigInt::from(-12).to_f64(), Some(-12.0));
+
+        // 2^53 + 1 is the first integer that can't be represented exactly
+        let exact = BigInt::from(1u64 << 53);
+        assert_eq!(exact.to_f64(), Some(9007199254740992.0));
+        let inexact = BigInt::from((1u64 << 53) + 1);
+        assert_eq!(inexact.to_f64(), Some(9007199254740992.0));
+
+        assert_eq!(
+            BigInt::from(std::u64::MAX).to_f64(),
+            Some(std::u64::MAX as f64)
+        );
+
+        let huge = BigInt::from(10).pow(200) * BigInt::from(10).pow(200);
+        assert_eq!(huge.to_f64(), None);
+        assert_eq!((-huge).to_f64(), None);
+    }
+
+    #[test]
+    fn big_decimal_with_scale_round() {
+        fn round(value: &str, scale: i64, mode: RoundingMode) -> BigDecimal {
+            BigDecimal::from_str(value)
+                .unwrap()
+                .with_scale_round(scale, mode)
+        }
+
+        let cases = vec![
+            ("2.345", RoundingMode::HalfUp, "2.35"),
+            ("2.345", RoundingMode::HalfEven, "2.34"),
+            ("2.345", RoundingMode::Floor, "2.34"),
+            ("2.345", RoundingMode::Ceil, "2.35"),
+            ("2.355", RoundingMode::HalfEven, "2.36"),
+            ("2.3451", RoundingMode::HalfEven, "2.35"),
+            ("-2.345", RoundingMode::HalfUp, "-2.35"),
+            ("-2.345", RoundingMode::HalfEven, "-2.34"),
+            ("-2.345", RoundingMode::Floor, "-2.35"),
+            ("-2.345", RoundingMode::Ceil, "-2.34"),
+            ("2.3", RoundingMode::Floor, "2.30"),
+            ("2", RoundingMode::Ceil, "2.00"),
+        ];
+        for (value, mode, expected) in cases {
+            let rounded = round(value, 2, mode);
+            assert_eq!(rounded.to_string(), expected, "{} {:?}", value, mode);
+            assert_eq!(rounded.as_bigint_and_exponent().1, 2);
+        }
+
+        assert_eq!(round("1234.5", 0, RoundingMode::HalfUp).to_string(), "1235");
+        assert_eq!(
+            round("1250", -2, RoundingMode::HalfEven),
+            BigDecimal::from(1200)
+        );
+
+        // Dropping all digits, even more than fit into an `i64`
+        let tiny = BigDecimal::new(BigInt::from(-5).0, std::i64::MAX);
+        for (scale, mode, expected) in vec![
+            (0, RoundingMode::HalfUp, 0),
+            (0, RoundingMode::Floor, -1),
+            (0, RoundingMode::Ceil, 0),
+            (-1, RoundingMode::HalfEven, 0),
+            (-1, RoundingMode::Floor, -10),
+        ] {
+            assert_eq!(
+                tiny.with_scale_round(scale, mode),
+                BigDecimal::from(expected),
+                "{} {:?}",
+                scale,
+                mode
+            );
+        }
+        // Comparing values whose scales are that far apart would not finish
+        assert_eq!(
+            (num_bigint::BigInt::from(0), std::i64::MIN),
+            tiny.with_scale_round(std::i64::MIN, RoundingMode::HalfUp)
+                .as_bigint_and_exponent()
+        );
+        assert_eq!(round("4", -1, RoundingMode::HalfUp), BigDecimal::from(0));
+        assert_eq!(round("5", -1, RoundingMode::HalfUp), BigDecimal::from(10));
+    }
+
+    #[test]
+    fn big_decimal_truncate_floor_ceil() {
+        let d = |s: &str| BigDecimal::from_str(s).unwrap();
+
+        assert_eq!(d("2.349").truncate(2), d("2.34"));
+        assert_eq!(d("-2.349").truncate(2), d("-2.34"));
+        assert_eq!(d("2.3").truncate(2), d("2.3"));
+        assert_eq!(d("1299").truncate(-2), d("1200"));
+        assert_eq!(d("-1.999").truncate(0), d("-1"));
+        assert_eq!(
+            (num_bigint::BigInt::from(0), std::i64::MIN),
+            d("-1.999").truncate(std::i64::MIN).as_bigint_and_exponent()
+        );
+        assert_eq!(
+            BigDecimal::new(BigInt::from(7).0, std::i64::MAX).truncate(-1),
+            d("0")
+        );
+
+        assert_eq!(d("1.5").floor(), BigInt::from(1));
+        assert_eq!(d("1.5").ceil(), BigInt::from(2));
+        assert_eq!(d("-1.5").floor(), BigInt::from(-2));
+        assert_eq!(d("-1.5").ceil(), BigInt::from(-1));
+        assert_eq!(d("3").floor(), BigInt::from(3));
+        assert_eq!(d("3.000").ceil(), BigInt::from(3));
+        assert_eq!(d("-0.1").ceil(), BigInt::from(0));
+        assert_eq!(BigDecimal::new(5.into(), -2).floor(), BigInt::from(500));
+    }
+
+    #[test]
+    fn big_decimal_checked_div() {
+        let third = BigDecimal::from(1)
+            .checked_div(&BigDecimal::from(3))
+            .unwrap();
+        assert_eq!(format!("0.{}", "3".repeat(100)), third.to_string());
+        let two_thirds = BigDecimal::from(2)
+            .checked_div(&BigDecimal::from(3))
+            .unwrap();
+        assert_eq!(format!("0.{}7", "6".repeat(99)), two_thirds.to_string());
+
+        // Very small quotients keep all their significant digits
+        let small = BigDecimal::from(1)
+            .checked_div(&BigDecimal::from_str("3e50").unwrap())
+            .unwrap();
+        assert_eq!(
+            format!("0.{}{}", "0".repeat(50), "3".repeat(100)),
+            small.to_string()
+        );
+
+        assert_eq!(
+            BigDecimal::from(1).checked_div(&BigDecimal::from(8)),
+            Ok(BigDecimal::from_str("0.125").unwrap())
+        );
+        assert_eq!(
+            BigDecimal::from(1).checked_div(&BigDecimal::from(0)),
+            Err(ArithmeticError::DivisionByZero)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot divide by zero-valued `BigDecimal`!")]
+    fn big_decimal_div_by_zero() {
+        let _ = BigDecimal::from(1) / BigDecimal::from(0);
+    }
+
+    #[test]
+    fn big_int_try_to_big_decimal() {
+        assert_eq!(
+            BigInt::from(15).try_to_big_decimal(BigInt::from(-1)),
+            Ok(BigDecimal::from_str("1.5").unwrap())
+        );
+        assert_eq!(
+            BigInt::from(15).try_to_big_decimal(BigInt::from(2)),
+            Ok(BigDecimal::from(1500))
+        );
+
+        let max = BigInt::from(std::i64::MAX);
+        assert!(BigInt::from(1).try_to_big_decimal(max.clone()).is_ok());
+        assert_eq!(
+            BigInt::from(1).try_to_big_decimal(max + BigInt::from(1)),
+            Err(ArithmeticError::ExponentOutOfRange)
+        );
+        assert_eq!(
+            BigInt::from(1).try_to_big_decimal(BigInt::from(std::i64::MIN)),
+            Err(ArithmeticError::ExponentOutOfRange)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "big decimal exponent does not fit in i64")]
+    fn big_int_to_big_decimal_out_of_range() {
+        BigInt::from(1).to_big_decimal(BigInt::from(std::i64::MAX) + BigInt::from(1));
+    }
+
+    #[test]
+    fn big_decimal_sqrt() {
+        let d = |s: &str| BigDecimal::from_str(s).unwrap();
+
+        assert_eq!(
+            BigDecimal::from(2).sqrt(10).unwrap().to_string(),
+            "1.4142135623"
+        );
+        assert_eq!(d("0.0004").sqrt(2).unwrap().to_string(), "0.02");
+        assert_eq!(d("6.25").sqrt(4).unwrap(), d("2.5"));
+        assert_eq!(
+            BigDecimal::from(1_000_000).sqrt(0).unwrap().to_string(),
+            "1000"
+        );
+        assert_eq!(BigDecimal::from(0).sqrt(3).unwrap(), BigDecimal::from(0));
+        assert_eq!(
+            BigDecimal::from(10_000).sqrt(-1).unwrap(),
+            BigDecimal::from(100)
+        );
+        assert_eq!(d("-1").sqrt(5), None);
+    }
+
+    #[test]
+    fn bytes_concat() {
+        let left = Bytes::from_str("0xdead").unwrap();
+        let right = Bytes::from_str("0xbeef").unwrap();
+        let empty = Bytes::from_str("0x").unwrap();
+
+        assert_eq!(left.len(), 2);
+        assert!(!left.is_empty());
+        assert!(empty.is_empty());
+
+        let joined = left.concat(&right);
+        assert_eq!(joined.len(), 4);
+        assert_eq!(joined.to_string(), "0xdeadbeef");
+        assert_eq!(left.clone() + right, joined);
+        assert_eq!(left.concat(&empty), left);
+        assert_eq!(empty.concat(&left), left);
+    }
+
+    #[test]
+    fn bytes_deref_and_from_vec() {
+        let vec: Vec<u8> = vec![0xde, 0xad, 0xbe, 0xef];
+        let from_slice = Bytes::from(vec.as_slice());
+        let from_vec = Bytes::from(vec.clone());
+        assert_eq!(from_slice, from_vec);
+
+        fn sum(bytes: &[u8]) -> u32 {
+            bytes.iter().map(|b| *b as u32).sum()
+        }
+        assert_eq!(sum(&from_vec), 0xde + 0xad + 0xbe + 0xef);
+        assert_eq!(from_vec.as_ref(), vec.as_slice());
+        assert_eq!(&from_vec[1..3], &[0xad, 0xbe]);
+        assert_eq!(from_vec.first(), Some(&0xde));
+
+        assert_eq!(from_vec.to_string(), "0xdeadbeef");
+        assert_eq!(serde_json::to_string(&from_vec).unwrap(), r#""0xdeadbeef""#);
     }
 
     #[test]
-    fn big_int_stable_hash_same_as_int() {
-        same_stable_hash(0, BigInt::from(0u64));
-        same_stable_hash(1, BigInt::from(1u64));
-        same_stable_hash(1u64 << 20, BigInt::from(1u64 << 20));
+    fn bytes_slice() {
+        let bytes = Bytes::from_str("0x00112233").unwrap();
+        assert_eq!(bytes.slice(1, 3).to_string(), "0x1122");
+        assert_eq!(bytes.slice(0, 4), bytes);
+        assert!(bytes.slice(2, 2).is_empty());
+        assert!(bytes.slice(4, 4).is_empty());
+    }
 
-        same_stable_hash(-1, BigInt::from_signed_bytes_le(&(-1i32).to_le_bytes()));
+    #[test]
+    #[should_panic(expected = "range 2..5 is out of bounds for Bytes of length 4")]
+    fn bytes_slice_out_of_bounds() {
+        Bytes::from_str("0x00112233").unwrap().slice(2, 5);
     }
 
-    struct BigDecimalStableHash(BigDecimal);
-    impl StableHash for BigDecimalStableHash {
-        fn stable_hash(&self, sequence_number: impl SequenceNumber, state: &mut impl StableHasher) {
-            big_decimal_stable_hash(&self.0, sequence_number, state);
+    #[test]
+    fn bytes_ct_eq_agrees_with_eq() {
+        use rand::Rng;
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000 {
+            // Use short values over a small alphabet so that equal values are
+            // generated frequently
+            let left: Vec<u8> = (0..rng.gen_range(0, 4))
+                .map(|_| rng.gen_range(0, 3))
+                .collect();
+            let right: Vec<u8> = (0..rng.gen_range(0, 4))
+                .map(|_| rng.gen_range(0, 3))
+                .collect();
+            let left = Bytes::from(left.as_slice());
+            let right = Bytes::from(right.as_slice());
+
+            assert_eq!(left.ct_eq(&right), left == right);
+            assert!(left.ct_eq(&left));
         }
     }
 
     #[test]
-    fn big_decimal_stable_hash_same_as_uint() {
-        same_stable_hash(0, BigDecimalStableHash(BigDecimal::from(0u64)));
-        same_stable_hash(4, BigDecimalStableHash(BigDecimal::from(4i64)));
-        same_stable_hash(
-            1u64 << 21,
-            BigDecimalStableHash(BigDecimal::from(1u64 << 21)),
+    fn bytes_ord() {
+        let b = |s: &str| Bytes::from_str(s).unwrap();
+
+        let mut values = vec![b("0x02"), b("0x0100"), b("0x"), b("0x01"), b("0xff")];
+        values.sort();
+        assert_eq!(
+            values,
+            vec![b("0x"), b("0x01"), b("0x0100"), b("0x02"), b("0xff")]
+        );
+
+        let set: BTreeSet<_> = vec![b("0xbeef"), b("0xdead"), b("0xBEEF")]
+            .into_iter()
+            .collect();
+        assert_eq!(set.len(), 2);
+        assert_eq!(
+            set.into_iter().collect::<Vec<_>>(),
+            vec![b("0xbeef"), b("0xdead")]
         );
     }
 
     #[test]
-    fn big_decimal_stable() {
-        let cases = vec![(5580731626265347763, "0.1"), (15037326160029728810, "-0.1")];
-        for case in cases.iter() {
-            let dec = BigDecimal::from_str(case.1).unwrap();
-            let dec = BigDecimalStableHash(dec);
-            assert_eq!(case.0, xx_stable_hash(dec));
+    fn bytes_base64() {
+        let bytes = Bytes::from_str("0xdeadbeef").unwrap();
+        assert_eq!(bytes.to_base64(), "3q2+7w==");
+        assert_eq!(Bytes::from_base64("3q2+7w==").unwrap(), bytes);
+
+        let empty = Bytes::from_str("0x").unwrap();
+        assert_eq!(Bytes::from_base64(&empty.to_base64()).unwrap(), empty);
+
+        assert!(Bytes::from_base64("not base64!").is_err());
+    }
+
+    #[test]
+    fn bytes_as_base64_serde() {
+        use serde_derive::{Deserialize, Serialize};
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Payload {
+            hex: Bytes,
+            #[serde(with = "super::bytes_as_base64")]
+            base64: Bytes,
+        }
+
+        let payload = Payload {
+            hex: Bytes::from_str("0xdeadbeef").unwrap(),
+            base64: Bytes::from_str("0xdeadbeef").unwrap(),
+        };
+        let json = serde_json::to_string(&payload).unwrap();
+        assert_eq!(json, r#"{"hex":"0xdeadbeef","base64":"3q2+7w=="}"#);
+        assert_eq!(serde_json::from_str::<Payload>(&json).unwrap(), payload);
+    }
+
+    #[test]
+    fn bytes_pad() {
+        let bytes = Bytes::from_str("0xdeadbeef").unwrap();
+        let zeros = "00".repeat(28);
+
+        assert_eq!(
+            bytes.left_pad(32).unwrap().to_string(),
+            format!("0x{}deadbeef", zeros)
+        );
+        assert_eq!(
+            bytes.right_pad(32).unwrap().to_string(),
+            format!("0xdeadbeef{}", zeros)
+        );
+        assert_eq!(bytes.left_pad(4).unwrap(), bytes);
+        assert_eq!(bytes.right_pad(4).unwrap(), bytes);
+        assert_eq!(
+            bytes.left_pad(3),
+            Err(BytesTooLongError {
+                len: 4,
+                target_len: 3
+            })
+        );
+        assert!(bytes.right_pad(0).is_err());
+    }
+
+    #[test]
+    fn sign_predicates() {
+        assert!(BigInt::from(0).is_zero());
+        assert!(!BigInt::from(0).is_positive());
+        assert!(!BigInt::from(0).is_negative());
+        assert!(BigInt::from(3).is_positive());
+        assert!(!BigInt::from(3).is_zero());
+        assert!(BigInt::from(-3).is_negative());
+        assert!((BigInt::from(3) - BigInt::from(3)).is_zero());
+
+        assert!(BigDecimal::from(0).is_zero());
+        assert!(BigDecimal::from_str("0.000").unwrap().is_zero());
+        assert!(!BigDecimal::from_str("0.001").unwrap().is_zero());
+    }
+
+    #[test]
+    fn min_max_clamp() {
+        let i = |n: i32| BigInt::from(n);
+        assert_eq!(i(3).min(i(-4)), i(-4));
+        assert_eq!(i(3).max(i(-4)), i(3));
+        assert_eq!(i(5).clamp(i(0), i(10)), i(5));
+        assert_eq!(i(-5).clamp(i(0), i(10)), i(0));
+        assert_eq!(i(15).clamp(i(0), i(10)), i(10));
+        assert_eq!(i(10).clamp(i(0), i(10)), i(10));
+        assert_eq!(i(7).clamp(i(7), i(7)), i(7));
+
+        let d = |s: &str| BigDecimal::from_str(s).unwrap();
+        assert_eq!(d("1.5").min(d("1.25")), d("1.25"));
+        assert_eq!(d("1.5").max(d("1.25")), d("1.5"));
+        assert_eq!(d("1.50").clamp(d("1.5"), d("2")), d("1.5"));
+        assert_eq!(d("2.000").clamp(d("1.5"), d("2")), d("2"));
+        assert_eq!(d("0.1").clamp(d("1.5"), d("2.00")), d("1.5"));
+        assert_eq!(d("7").clamp(d("1.5"), d("2.00")), d("2"));
+    }
+
+    #[test]
+    #[should_panic(expected = "clamp: lo `2` is greater than hi `1`")]
+    fn clamp_panics_if_lo_greater_than_hi() {
+        BigInt::from(0).clamp(BigInt::from(2), BigInt::from(1));
+    }
+
+    #[test]
+    fn big_int_as_number_serde() {
+        use serde_derive::{Deserialize, Serialize};
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Amount {
+            #[serde(with = "super::big_int_as_number")]
+            value: BigInt,
+        }
+
+        let check = |value: BigInt, json: &str| {
+            let amount = Amount { value };
+            assert_eq!(serde_json::to_string(&amount).unwrap(), json);
+            assert_eq!(serde_json::from_str::<Amount>(json).unwrap(), amount);
+        };
+
+        check(BigInt::from(42), r#"{"value":42}"#);
+        check(BigInt::from(-42), r#"{"value":-42}"#);
+        check(
+            BigInt::from(std::u64::MAX),
+            r#"{"value":18446744073709551615}"#,
+        );
+        let large = BigInt::from(2).pow(255);
+        check(
+            large.clone(),
+            &format!(r#"{{"value":"{}"}}"#, large.to_string()),
+        );
+
+        // Strings for small values and large numbers are accepted, too
+        assert_eq!(
+            serde_json::from_str::<Amount>(r#"{"value":"42"}"#).unwrap(),
+            Amount {
+                value: BigInt::from(42)
+            }
+        );
+        assert_eq!(
+            serde_json::from_str::<Amount>(&format!(r#"{{"value":{}}}"#, large)).unwrap(),
+            Amount { value: large }
+        );
+        assert!(serde_json::from_str::<Amount>(r#"{"value":1.5}"#).is_err());
+        assert!(serde_json::from_str::<Amount>(r#"{"value":true}"#).is_err());
+    }
+
+    #[test]
+    fn big_int_pow() {
+        let two_300 = BigInt::from(2).pow(300);
+        assert_eq!(two_300.bits(), 301);
+        assert_eq!(two_300, BigInt::from(1) << 300);
+        assert_eq!(BigInt::from(-3).pow(3), BigInt::from(-27));
+        assert_eq!(BigInt::from(7).pow(0), BigInt::from(1));
+
+        assert_eq!(BigInt::from(2).checked_pow(300, 301), Ok(two_300));
+        assert_eq!(
+            BigInt::from(2).checked_pow(300, 300),
+            Err(ArithmeticError::Overflow)
+        );
+        // Rejected without computing the result
+        assert_eq!(
+            BigInt::from(10).checked_pow(std::u32::MAX, 256),
+            Err(ArithmeticError::Overflow)
+        );
+        assert_eq!(
+            BigInt::from(-1).checked_pow(std::u32::MAX, 1),
+            Ok(BigInt::from(-1))
+        );
+        assert_eq!(
+            BigInt::from(0).checked_pow(std::u32::MAX, 0),
+            Ok(BigInt::from(0))
+        );
+    }
+
+    #[test]
+    fn big_decimal_with_scale() {
+        let x = BigDecimal::from_str("1.5").unwrap();
+
+        let padded = x.with_scale(4);
+        assert_eq!("1.5000", padded.to_string());
+        assert_eq!(4, padded.as_bigint_and_exponent().1);
+        assert_eq!(x, padded);
+
+        let back = padded.with_scale(1);
+        assert_eq!("1.5", back.to_string());
+        assert_eq!(1, back.as_bigint_and_exponent().1);
+
+        // Decreasing the scale truncates
+        let x = BigDecimal::from_str("-1.59").unwrap();
+        assert_eq!("-1.5", x.with_scale(1).to_string());
+    }
+
+    #[test]
+    fn big_decimal_percent_and_basis_points() {
+        let part = BigDecimal::from(50);
+        let whole = BigDecimal::from(200);
+        assert_eq!(BigDecimal::from(25), part.percent_of(&whole));
+        assert_eq!(BigDecimal::from(2500), part.basis_points_of(&whole));
+
+        let third = BigDecimal::from(1).percent_of(&BigDecimal::from(3));
+        assert_eq!(BigDecimal::from_str("33.33").unwrap(), third.truncate(2));
+
+        let zero = BigDecimal::from(0);
+        assert_eq!(zero, part.percent_of(&zero));
+        assert_eq!(zero, part.basis_points_of(&zero));
+    }
+
+    #[test]
+    fn bigint_from_128_bit_ints() {
+        let values = vec![
+            (
+                BigInt::from(std::i128::MIN),
+                "-170141183460469231731687303715884105728",
+            ),
+            (
+                BigInt::from(std::i128::MAX),
+                "170141183460469231731687303715884105727",
+            ),
+            (
+                BigInt::from(std::u128::MAX),
+                "340282366920938463463374607431768211455",
+            ),
+        ];
+        for (n, decimal) in values {
+            assert_eq!(BigInt::from_str(decimal).unwrap(), n);
+            assert_eq!(n, BigInt::from_signed_bytes_le(&n.to_signed_bytes_le()));
+        }
+    }
+
+    #[test]
+    fn bigint_signed_u128_round_trip() {
+        for n in &[
+            BigInt::from(-1),
+            BigInt::from(-12345),
+            BigInt::from(std::i128::MIN),
+            BigInt::from(std::i128::MAX),
+            BigInt::from(0),
+        ] {
+            let u = n.to_signed_u128().unwrap();
+            assert_eq!(n, &BigInt::from_signed_u128(&u));
+        }
+
+        // -1 is all ones in two's complement
+        assert_eq!(
+            U128::max_value(),
+            BigInt::from(-1).to_signed_u128().unwrap()
+        );
+        // The sign bit makes values with bit 127 set negative
+        assert_eq!(
+            BigInt::from(std::i128::MIN),
+            BigInt::from_signed_u128(&(U128::from(1) << 127))
+        );
+
+        assert!(matches!(
+            (BigInt::from(std::i128::MAX) + BigInt::from(1)).to_signed_u128(),
+            Err(BigIntOutOfRangeError::Overflow)
+        ));
+        assert!(matches!(
+            (BigInt::from(std::i128::MIN) - BigInt::from(1)).to_signed_u128(),
+            Err(BigIntOutOfRangeError::Overflow)
+        ));
+    }
+
+    #[test]
+    fn bigint_hex_round_trip() {
+        let n = BigInt::from(255);
+        assert_eq!("0xff", n.to_hex());
+        assert_eq!(n, BigInt::from_hex("0xff").unwrap());
+        assert_eq!(n, BigInt::from_hex("ff").unwrap());
+
+        let n = BigInt::from(-4096);
+        assert_eq!("-0x1000", n.to_hex());
+        assert_eq!(n, BigInt::from_hex(&n.to_hex()).unwrap());
+
+        assert_eq!("0x0", BigInt::from(0).to_hex());
+        assert!(BigInt::from_hex("0xfg").is_err());
+    }
+
+    #[test]
+    fn bytes_from_str() {
+        let expected = Bytes::from(vec![0xab]);
+        assert_eq!(expected, Bytes::from_str("0xAB").unwrap());
+        assert_eq!(expected, Bytes::from_str("0xab").unwrap());
+        assert_eq!(expected, Bytes::from_str("Ab").unwrap());
+
+        let empty: Vec<u8> = vec![];
+        assert_eq!(Bytes::from(empty.clone()), Bytes::from_str("0x").unwrap());
+        assert_eq!(Bytes::from(empty), Bytes::from_str("").unwrap());
+
+        let err = Bytes::from_str("0xabc").unwrap_err();
+        assert_eq!(
+            ParseBytesError {
+                input: "0xabc".to_owned(),
+                error: hex::FromHexError::OddLength,
+            },
+            err
+        );
+        assert!(err.to_string().starts_with("invalid Bytes value `0xabc`: "));
+
+        assert!(Bytes::from_str("0xzz").is_err());
+    }
+
+    #[test]
+    fn big_decimal_from_bytes_le() {
+        for s in &["0", "1.5", "-1.5", "123456789.000000000001", "-0.0001"] {
+            let x = BigDecimal::from_str(s).unwrap();
+            let (digits, exp) = x.as_bigint_and_exponent();
+            let y = BigDecimal::from_bytes_le(&digits.to_signed_bytes_le(), true, exp);
+            assert_eq!(x, y);
+            assert_eq!((digits, exp), y.as_bigint_and_exponent());
+        }
+
+        // Unsigned bytes never produce a negative value
+        let x = BigDecimal::from_bytes_le(&[0xff], false, 1);
+        assert_eq!(BigDecimal::from_str("25.5").unwrap(), x);
+        let x = BigDecimal::from_bytes_le(&[0xff], true, 1);
+        assert_eq!(BigDecimal::from_str("-0.1").unwrap(), x);
+    }
+
+    #[test]
+    fn big_decimal_rem() {
+        fn rem(a: &str, b: &str) -> BigDecimal {
+            BigDecimal::from_str(a).unwrap() % BigDecimal::from_str(b).unwrap()
+        }
+
+        assert_eq!(BigDecimal::from_str("1.5").unwrap(), rem("5.5", "2"));
+        assert_eq!(BigDecimal::from_str("0.05").unwrap(), rem("1", "0.19"));
+        assert_eq!(BigDecimal::from(0), rem("6", "1.5"));
+        // The sign of the remainder follows the dividend
+        assert_eq!(BigDecimal::from_str("-1.5").unwrap(), rem("-5.5", "2"));
+        assert_eq!(BigDecimal::from_str("1.5").unwrap(), rem("5.5", "-2"));
+        assert_eq!(BigDecimal::from(6), rem("1E3", "7"));
+
+        let mut x = BigDecimal::from_str("7.25").unwrap();
+        x %= BigDecimal::from(2);
+        assert_eq!(BigDecimal::from_str("1.25").unwrap(), x);
+
+        assert_eq!(
+            Err(ArithmeticError::DivisionByZero),
+            BigDecimal::from(1).checked_rem(&BigDecimal::from(0))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot divide by zero-valued `BigDecimal`!")]
+    fn big_decimal_rem_by_zero() {
+        let _ = BigDecimal::from(1) % BigDecimal::from(0);
+    }
+
+    #[test]
+    fn big_int_try_from_big_decimal() {
+        fn big_int(s: &str) -> Result<BigInt, BigIntOutOfRangeError> {
+            BigInt::try_from(BigDecimal::from_str(s).unwrap())
+        }
+
+        assert_eq!(BigInt::from(42), big_int("42.0").unwrap());
+        assert_eq!(BigInt::from(-42), big_int("-42.000").unwrap());
+        assert_eq!(BigInt::from(4200), big_int("42e2").unwrap());
+        assert_eq!(BigInt::from(0), big_int("0.0").unwrap());
+        assert!(matches!(
+            big_int("42.5"),
+            Err(BigIntOutOfRangeError::Fractional)
+        ));
+        assert!(matches!(
+            big_int("-0.001"),
+            Err(BigIntOutOfRangeError::Fractional)
+        ));
+    }
+
+    #[test]
+    fn big_decimal_scientific_notation() {
+        fn parse(s: &str) -> BigDecimal {
+            BigDecimal::from_str(s).unwrap()
+        }
+
+        // Parsing scientific notation gives the same value as the plain
+        // decimal representation
+        assert_eq!(parse("0.00000000123"), parse("1.23e-9"));
+        assert_eq!("0.00000000123", parse("1.23e-9").to_string());
+        assert_eq!(parse("10000000000"), parse("1E10"));
+        assert_eq!(parse("-4500"), parse("-4.5e3"));
+        assert_eq!(parse("-0.045"), parse("-4.5E-2"));
+
+        assert_eq!("1.23e-9", parse("1.23e-9").to_scientific_string());
+        assert_eq!("1e10", parse("1E10").to_scientific_string());
+        assert_eq!("-4.5e3", parse("-4.5e3").to_scientific_string());
+        assert_eq!("1.5e0", parse("1.500").to_scientific_string());
+        assert_eq!("1.2345e2", parse("123.45").to_scientific_string());
+        assert_eq!("0e0", parse("0.000").to_scientific_string());
+
+        for s in &["1.23e-9", "1E10", "-4.5e3", "123.45", "-0.001", "0"] {
+            let x = parse(s);
+            assert_eq!(x, parse(&x.to_scientific_string()));
+        }
+    }
+
+    #[test]
+    fn big_decimal_abs_signum() {
+        let x = BigDecimal::from_str("-3.14").unwrap();
+        assert_eq!(BigDecimal::from_str("3.14").unwrap(), x.abs());
+        assert_eq!(
+            x.as_bigint_and_exponent().1,
+            x.abs().as_bigint_and_exponent().1
+        );
+        assert_eq!(-1, x.signum());
+
+        assert_eq!(BigDecimal::from(0), BigDecimal::from(0).abs());
+        assert_eq!(0, BigDecimal::from(0).signum());
+        assert_eq!(0, BigDecimal::from_str("0.000").unwrap().signum());
+
+        let x = BigDecimal::from_str("2.5").unwrap();
+        assert_eq!(x, x.abs());
+        assert_eq!(1, x.signum());
+    }
+
+    #[test]
+    fn bytes_from_web3_types() {
+        let hex = "cf9e4ef9c1d2b6f4a5bbec7ac3b8c4ea1d72b01db5f8e1f6d5f3b2ca3fa2c701";
+        let hash = H256::from_str(hex).unwrap();
+        let bytes = Bytes::from(hash);
+        assert_eq!(32, bytes.as_slice().len());
+        assert_eq!(format!("0x{}", hex), bytes.to_string());
+        assert_eq!(bytes, Bytes::from_str(&bytes.to_string()).unwrap());
+        assert_eq!(hash, H256::from_slice(bytes.as_slice()));
+
+        let bytes = Bytes::from(U256::from(258));
+        assert_eq!(format!("0x{}0102", "00".repeat(30)), bytes.to_string());
+        assert_eq!(bytes, Bytes::from_str(&bytes.to_string()).unwrap());
+        assert_eq!(U256::from(258), U256::from_big_endian(bytes.as_slice()));
+
+        let bytes = Bytes::from(U256::max_value());
+        assert_eq!(format!("0x{}", "ff".repeat(32)), bytes.to_string());
+    }
+
+    #[test]
+    fn bytes_list_stable_hash() {
+        fn hash(list: &[&str]) -> u64 {
+            let list: Vec<_> = list.iter().map(|s| Bytes::from_str(s).unwrap()).collect();
+            stable_hash_with_hasher::<XxHash64, _>(&BytesList(&list))
+        }
+
+        // The same bytes, grouped differently
+        assert_ne!(hash(&["0x01", "0x0203"]), hash(&["0x0102", "0x03"]));
+        assert_ne!(hash(&["0x010203"]), hash(&["0x01", "0x0203"]));
+        assert_ne!(hash(&["0x", "0x01"]), hash(&["0x01", "0x"]));
+        assert_eq!(hash(&["0x01", "0x0203"]), hash(&["0x01", "0x0203"]));
+    }
+
+    #[test]
+    fn big_int_byte_len() {
+        assert_eq!(1, BigInt::from(0).byte_len());
+        assert_eq!(1, BigInt::from(255).byte_len());
+        assert_eq!(2, BigInt::from(256).byte_len());
+        assert_eq!(1, BigInt::from(-255).byte_len());
+        assert_eq!(8, BigInt::from(std::u64::MAX).byte_len());
+        assert_eq!(
+            9,
+            (BigInt::from(std::u64::MAX) + BigInt::from(1)).byte_len()
+        );
+        assert_eq!(16, BigInt::from(std::u128::MAX).byte_len());
+    }
+
+    #[test]
+    fn big_int_to_str_radix() {
+        let x = BigInt::from(1_295_i32);
+        assert_eq!("10100001111", x.to_str_radix(2));
+        assert_eq!("50f", x.to_str_radix(16));
+        assert_eq!("zz", x.to_str_radix(36));
+        assert_eq!("-zz", (-x.clone()).to_str_radix(36));
+        assert_eq!("0", BigInt::from(0).to_str_radix(36));
+
+        for radix in &[2, 16, 36] {
+            let s = x.to_str_radix(*radix);
+            assert_eq!(x, BigInt::from_str_radix(&s, *radix).unwrap());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "radix must be between 2 and 36, but is 37")]
+    fn big_int_to_str_radix_invalid() {
+        BigInt::from(1).to_str_radix(37);
+    }
+
+    #[test]
+    fn big_decimal_compare_value() {
+        use std::cmp::Ordering;
+
+        // Deliberately not normalized: 10 * 10^-1
+        let ten_tenths = BigDecimal(bigdecimal::BigDecimal::new(10.into(), 1));
+        let one = BigDecimal::from(1);
+
+        assert!(ten_tenths.eq_value(&one));
+        assert_eq!(Ordering::Equal, ten_tenths.cmp_value(&one));
+        // The derived traits agree
+        assert_eq!(ten_tenths, one);
+        assert_eq!(Ordering::Equal, ten_tenths.cmp(&one));
+
+        let x = BigDecimal::from_str("1.01").unwrap();
+        assert!(!x.eq_value(&ten_tenths));
+        assert_eq!(Ordering::Greater, x.cmp_value(&ten_tenths));
+        assert_eq!(Ordering::Less, ten_tenths.cmp_value(&x));
+        assert_eq!(
+            Ordering::Less,
+            BigDecimal::from_str("-1E3")
+                .unwrap()
+                .cmp_value(&BigDecimal::from_str("-999.99").unwrap())
+        );
+    }
+
+    #[test]
+    fn big_decimal_is_integer() {
+        fn is_integer(s: &str) -> bool {
+            BigDecimal::from_str(s).unwrap().is_integer()
+        }
+
+        assert!(is_integer("4"));
+        assert!(is_integer("4.0"));
+        assert!(is_integer("-4.000"));
+        assert!(is_integer("4e3"));
+        assert!(is_integer("0"));
+        assert!(is_integer("0.00"));
+        assert!(!is_integer("4.5"));
+        assert!(!is_integer("-0.001"));
+        assert!(!is_integer("4.25e1"));
+    }
+
+    #[test]
+    fn big_int_saturating() {
+        assert_eq!(0, BigInt::from(-5).saturating_to_u64());
+        assert_eq!(0, BigInt::from(0).saturating_to_u64());
+        assert_eq!(42, BigInt::from(42).saturating_to_u64());
+        assert_eq!(
+            std::u64::MAX,
+            BigInt::from(std::u64::MAX).saturating_to_u64()
+        );
+        assert_eq!(
+            std::u64::MAX,
+            (BigInt::from(std::u64::MAX) + BigInt::from(1)).saturating_to_u64()
+        );
+        assert_eq!(
+            std::u64::MAX,
+            BigInt::from(std::u128::MAX).saturating_to_u64()
+        );
+
+        let lo = BigInt::from(0);
+        let hi = BigInt::from(100);
+        assert_eq!(
+            lo,
+            BigInt::from(-5).saturating_clamp(lo.clone(), hi.clone())
+        );
+        assert_eq!(
+            hi,
+            BigInt::from(500).saturating_clamp(lo.clone(), hi.clone())
+        );
+        assert_eq!(BigInt::from(50), BigInt::from(50).saturating_clamp(lo, hi));
+    }
+
+    #[test]
+    fn sum_and_product() {
+        let ints: Vec<_> = vec![2, 3, 7].into_iter().map(BigInt::from).collect();
+        assert_eq!(BigInt::from(12), ints.iter().sum());
+        assert_eq!(BigInt::from(12), ints.iter().cloned().sum());
+        assert_eq!(BigInt::from(42), ints.iter().product());
+        assert_eq!(BigInt::from(42), ints.into_iter().product());
+        assert_eq!(BigInt::from(0), Vec::<BigInt>::new().into_iter().sum());
+        assert_eq!(BigInt::from(1), Vec::<BigInt>::new().into_iter().product());
+
+        let decimals: Vec<_> = vec!["1.5", "2", "-0.25"]
+            .into_iter()
+            .map(|s| BigDecimal::from_str(s).unwrap())
+            .collect();
+        assert_eq!(BigDecimal::from_str("3.25").unwrap(), decimals.iter().sum());
+        assert_eq!(
+            BigDecimal::from_str("-0.75").unwrap(),
+            decimals.iter().cloned().product()
+        );
+        assert_eq!(BigDecimal::from(0), Vec::<BigDecimal>::new().iter().sum());
+        assert_eq!(
+            BigDecimal::from(1),
+            Vec::<BigDecimal>::new().iter().product()
+        );
+    }
+
+    #[test]
+    fn big_int_to_radix() {
+        // Decimal digits, as used when normalizing `BigDecimal`
+        let n = BigInt::from(-12345);
+        assert_eq!((BigIntSign::Minus, vec![1, 2, 3, 4, 5]), n.to_radix_be(10));
+        assert_eq!((BigIntSign::Minus, vec![5, 4, 3, 2, 1]), n.to_radix_le(10));
+
+        // 3365 = 1 * 58^2 + 0 * 58 + 1
+        let n = BigInt::from(3365);
+        assert_eq!((BigIntSign::Plus, vec![1, 0, 1]), n.to_radix_be(58));
+        assert_eq!(
+            (BigIntSign::Plus, vec![255, 255]),
+            BigInt::from(65535).to_radix_be(256)
+        );
+        assert_eq!(
+            (BigIntSign::NoSign, vec![0]),
+            BigInt::from(0).to_radix_le(58)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "radix must be between 2 and 256, but is 257")]
+    fn big_int_to_radix_invalid() {
+        BigInt::from(1).to_radix_be(257);
+    }
+
+    #[test]
+    fn big_int_ten_pow() {
+        assert_eq!(BigInt::from(1), BigInt::ten_pow(0));
+        assert_eq!(BigInt::from(10), BigInt::ten_pow(1));
+        assert_eq!(
+            BigInt::from(1_000_000_000_000_000_000u64),
+            BigInt::ten_pow(18)
+        );
+        assert_eq!(BigInt::from(10).pow(18), BigInt::ten_pow(18));
+        // Beyond the precomputed values
+        assert_eq!(BigInt::from(10).pow(37), BigInt::ten_pow(37));
+        assert_eq!(BigInt::from(10).pow(255), BigInt::ten_pow(255));
+    }
+
+    #[test]
+    fn big_int_from_small_ints() {
+        assert_eq!(BigInt::from(255), BigInt::from(std::u8::MAX));
+        assert_eq!(BigInt::from(65535), BigInt::from(std::u16::MAX));
+        assert_eq!(BigInt::from(4_294_967_295u64), BigInt::from(std::u32::MAX));
+        assert_eq!(BigInt::from(0), BigInt::from(0u8));
+        assert_eq!(BigInt::from(1), BigInt::from(true));
+        assert_eq!(BigInt::from(0), BigInt::from(false));
+    }
+
+    #[test]
+    fn big_decimal_div_with_precision() {
+        fn d(s: &str) -> BigDecimal {
+            BigDecimal::from_str(s).unwrap()
+        }
+
+        let third = BigDecimal::from(1).div_with_precision(&BigDecimal::from(3), 20);
+        assert_eq!(
+            (num_bigint::BigInt::from(33_333_333_333_333_333_333u128), 20),
+            third.as_bigint_and_exponent()
+        );
+        assert_eq!("0.33333333333333333333", third.to_string());
+
+        // The default division always produces the same number of digits
+        let third = BigDecimal::from(1) / BigDecimal::from(3);
+        let (_, scale) = third.as_bigint_and_exponent();
+        assert_eq!(BigDecimal::DIVISION_PRECISION as i64, scale);
+        assert_eq!(BigDecimal::DIVISION_PRECISION, third.digits());
+        assert_eq!(third, BigDecimal::from(1) / BigDecimal::from(3));
+
+        // The precision counts significant digits, not digits after the
+        // decimal point
+        let small = d("1e-60") / d("3e50");
+        let (digits, scale) = small.as_bigint_and_exponent();
+        assert_eq!("3".repeat(100), digits.to_string());
+        assert_eq!(210, scale);
+        let large = d("1e60") / d("3");
+        let (digits, scale) = large.as_bigint_and_exponent();
+        assert_eq!("3".repeat(100), digits.to_string());
+        assert_eq!(40, scale);
+
+        // Quotients are rounded half up with the next digit
+        assert_eq!(d("0.67"), d("2").div_with_precision(&d("3"), 2));
+        assert_eq!(d("-0.67"), d("-2").div_with_precision(&d("3"), 2));
+        assert_eq!(d("0.33"), d("1").div_with_precision(&d("3"), 2));
+        assert_eq!(d("4"), d("7").div_with_precision(&d("2"), 1));
+        assert_eq!(d("3.5"), d("7").div_with_precision(&d("2"), 2));
+
+        // Terminating quotients keep their natural scale
+        let eighth = d("1").div_with_precision(&d("8"), 10);
+        assert_eq!(
+            (num_bigint::BigInt::from(125), 3),
+            eighth.as_bigint_and_exponent()
+        );
+        assert_eq!(d("2500"), d("1e4").div_with_precision(&d("4"), 10));
+        assert_eq!(d("12.5"), d("0.5").div_with_precision(&d("0.04"), 10));
+        assert_eq!(d("0.00000333"), d("1").div_with_precision(&d("3e5"), 3));
+
+        // The work does not depend on the exponents of the operands, and
+        // scales that do not fit into an `i64` are an error
+        let huge = d("1e1000000000") / d("3");
+        assert_eq!(100, huge.digits());
+        assert_eq!(
+            Err(ArithmeticError::ExponentOutOfRange),
+            BigDecimal::new(num_bigint::BigInt::from(1), std::i64::MAX)
+                .checked_div(&BigDecimal::new(num_bigint::BigInt::from(3), -1))
+        );
+    }
+
+    #[test]
+    fn big_decimal_try_from_f64() {
+        assert_eq!(
+            Err(ArithmeticError::NotFinite),
+            BigDecimal::try_from_f64(std::f64::NAN)
+        );
+        assert_eq!(
+            Err(ArithmeticError::NotFinite),
+            BigDecimal::try_from_f64(std::f64::INFINITY)
+        );
+        assert_eq!(
+            Err(ArithmeticError::NotFinite),
+            BigDecimal::try_from_f64(std::f64::NEG_INFINITY)
+        );
+        assert_eq!(
+            Ok(BigDecimal::from_str("-2.5").unwrap()),
+            BigDecimal::try_from_f64(-2.5)
+        );
+    }
+
+    #[test]
+    fn big_decimal_from_raw_token_amount() {
+        fn d(s: &str) -> BigDecimal {
+            BigDecimal::from_str(s).unwrap()
         }
+
+        let amount = |raw: i64, decimals| BigDecimal::from_raw_token_amount(&raw.into(), decimals);
+
+        assert_eq!(d("1.0"), amount(1_000_000, 6));
+        assert_eq!(d("1.5"), amount(1_500_000, 6));
+        assert_eq!(d("-0.000001"), amount(-1, 6));
+        assert_eq!(d("0.000000000000000001"), amount(1, 18));
+        // A token without decimals
+        assert_eq!(d("42"), amount(42, 0));
+        assert_eq!(BigDecimal::from(0), amount(0, 18));
+    }
+
+    #[test]
+    fn big_int_try_into_128_bits() {
+        let min = BigInt::from(std::i128::MIN);
+        assert_eq!(std::i128::MIN, i128::try_from(&min).unwrap());
+        assert!(matches!(
+            i128::try_from(min - BigInt::from(1)),
+            Err(BigIntOutOfRangeError::Overflow)
+        ));
+        assert!(matches!(
+            i128::try_from(BigInt::from(std::i128::MAX) + BigInt::from(1)),
+            Err(BigIntOutOfRangeError::Overflow)
+        ));
+
+        let max = BigInt::from(std::u128::MAX);
+        assert_eq!(std::u128::MAX, u128::try_from(&max).unwrap());
+        assert!(matches!(
+            u128::try_from(max + BigInt::from(1)),
+            Err(BigIntOutOfRangeError::Overflow)
+        ));
+        assert!(matches!(
+            u128::try_from(BigInt::from(-1)),
+            Err(BigIntOutOfRangeError::Negative)
+        ));
+        assert_eq!(0u128, u128::try_from(BigInt::from(0)).unwrap());
+    }
+
+    #[test]
+    fn big_int_with_bytes_le() {
+        for n in &[0i64, 1, -1, 255, 256, -65536, std::i64::MAX, std::i64::MIN] {
+            let n = BigInt::from(*n);
+            let (sign, bytes) = n.to_bytes_le();
+            n.with_bytes_le(|s, b| {
+                assert_eq!(sign, s);
+                assert_eq!(bytes.as_slice(), b);
+            });
+        }
+        assert_eq!(2, BigInt::from(256).with_bytes_le(|_, bytes| bytes.len()));
+    }
+
+    #[test]
+    fn big_decimal_debug_repr() {
+        assert_eq!(
+            "BigDecimal { unscaled: 1500, scale: 3 }",
+            BigDecimal::from_str("1.500").unwrap().debug_repr()
+        );
+        assert_eq!(
+            "BigDecimal { unscaled: 15, scale: 1 }",
+            BigDecimal::from_str("1.5").unwrap().debug_repr()
+        );
+        assert_eq!(
+            "BigDecimal { unscaled: -1324, scale: -6 }",
+            BigDecimal::from_str("-1324e6").unwrap().debug_repr()
+        );
+        // The user-facing representation does not change
+        assert_eq!("1.500", BigDecimal::from_str("1.500").unwrap().to_string());
+    }
+
+    #[test]
+    fn big_int_checked_pow_bounded() {
+        let max_u256 = BigInt::from(2).pow(256) - BigInt::from(1);
+
+        assert_eq!(
+            Ok(BigInt::from(2).pow(255)),
+            BigInt::from(2).checked_pow_bounded(255, &max_u256)
+        );
+        assert_eq!(
+            Ok(BigInt::from(-27)),
+            BigInt::from(-3).checked_pow_bounded(3, &BigInt::from(27))
+        );
+        assert_eq!(
+            Ok(BigInt::from(1)),
+            BigInt::from(0).checked_pow_bounded(0, &BigInt::from(1))
+        );
+        assert_eq!(
+            Ok(BigInt::from(0)),
+            BigInt::from(0).checked_pow_bounded(5, &BigInt::from(0))
+        );
+
+        // Just above the ceiling
+        assert_eq!(
+            Err(ArithmeticError::Overflow),
+            BigInt::from(2).checked_pow_bounded(256, &max_u256)
+        );
+        assert_eq!(
+            Err(ArithmeticError::Overflow),
+            BigInt::from(-3).checked_pow_bounded(3, &BigInt::from(26))
+        );
+        // Far above the ceiling, which is detected long before the result
+        // is computed
+        assert_eq!(
+            Err(ArithmeticError::Overflow),
+            BigInt::from(10).checked_pow_bounded(std::u32::MAX, &max_u256)
+        );
+    }
+
+    #[test]
+    fn big_decimal_to_raw_fixed_point() {
+        fn d(s: &str) -> BigDecimal {
+            BigDecimal::from_str(s).unwrap()
+        }
+
+        assert_eq!(Ok(BigInt::from(150)), d("1.5").to_raw_fixed_point(2));
+        assert_eq!(Ok(BigInt::from(-150)), d("-1.5").to_raw_fixed_point(2));
+        assert_eq!(Ok(BigInt::from(7)), d("7").to_raw_fixed_point(0));
+        assert_eq!(Ok(BigInt::from(1200)), d("1.2e3").to_raw_fixed_point(0));
+        // Trailing zeros beyond the decimals are fine
+        assert_eq!(Ok(BigInt::from(125)), d("1.2500").to_raw_fixed_point(2));
+        assert_eq!(
+            Err(ArithmeticError::PrecisionLoss),
+            d("1.125").to_raw_fixed_point(2)
+        );
+
+        // Round trip with `from_raw_token_amount`
+        let raw = BigInt::from(123_456_789);
+        assert_eq!(
+            Ok(raw.clone()),
+            BigDecimal::from_raw_token_amount(&raw, 6).to_raw_fixed_point(6)
+        );
+    }
+
+    #[test]
+    fn big_int_decimal_digits() {
+        assert_eq!(vec![1, 2, 3, 4, 5], BigInt::from(12345).decimal_digits());
+        assert_eq!(vec![1, 2, 3, 4, 5], BigInt::from(-12345).decimal_digits());
+        assert_eq!(vec![0], BigInt::from(0).decimal_digits());
+    }
+
+    #[test]
+    fn big_number_json_values() {
+        use serde_json::json;
+
+        assert_eq!(Ok(BigInt::from(42)), BigInt::from_json_value(&json!(42)));
+        assert_eq!(
+            Ok(BigInt::from(-42)),
+            BigInt::from_json_value(&json!("-42"))
+        );
+        let big = "123456789012345678901234567890";
+        assert_eq!(
+            Ok(BigInt::from_str(big).unwrap()),
+            BigInt::from_json_value(&json!(big))
+        );
+        assert_eq!(
+            Err(InvalidJsonNumber("\"0x2a\"".to_owned())),
+            BigInt::from_json_value(&json!("0x2a"))
+        );
+        assert!(BigInt::from_json_value(&json!(1.5)).is_err());
+        assert!(BigInt::from_json_value(&json!(null)).is_err());
+        assert_eq!(json!("42"), BigInt::from(42).to_json_value());
+
+        let d = BigDecimal::from_str("1.5").unwrap();
+        assert_eq!(Ok(d.clone()), BigDecimal::from_json_value(&json!(1.5)));
+        assert_eq!(Ok(d.clone()), BigDecimal::from_json_value(&json!("1.5")));
+        assert_eq!(
+            Err(InvalidJsonNumber("true".to_owned())),
+            BigDecimal::from_json_value(&json!(true))
+        );
+        assert!(BigDecimal::from_json_value(&json!("one")).is_err());
+        assert_eq!(json!("1.5"), d.to_json_value());
+    }
+
+    #[test]
+    fn big_decimal_to_canonical_string() {
+        fn canonical(s: &str) -> String {
+            BigDecimal::from_str(s).unwrap().to_canonical_string()
+        }
+
+        assert_eq!("1900", canonical("1900"));
+        assert_eq!("1900", canonical("19E2"));
+        assert_eq!("1900", canonical("1900.000"));
+        assert_eq!("0.1", canonical("0.1"));
+        assert_eq!("0.1", canonical("0.1000"));
+        assert_eq!("-0.1", canonical("-0.1"));
+        assert_eq!("0", canonical("0"));
+        assert_eq!("0", canonical("0.000"));
+        assert_eq!("0", canonical("-0e5"));
+        assert_eq!("12.34", canonical("1234e-2"));
+        assert_eq!("0.00012", canonical("1.2e-4"));
+        assert_eq!("-123", canonical("-123"));
+        assert_eq!(
+            "0",
+            BigDecimal::new(num_bigint::BigInt::from(0), -5).to_canonical_string()
+        );
+    }
+
+    #[test]
+    fn big_int_try_to_u256() {
+        let max = BigInt::from_unsigned_u256(&U256::max_value());
+        let too_large = max.clone() + BigInt::from(1);
+
+        assert_eq!(Ok(U256::max_value()), max.try_to_unsigned_u256());
+        assert_eq!(Ok(U256::from(42)), BigInt::from(42).try_to_unsigned_u256());
+        assert_eq!(
+            Err(ArithmeticError::Overflow),
+            too_large.try_to_unsigned_u256()
+        );
+        assert_eq!(
+            Err(ArithmeticError::NegativeValue),
+            BigInt::from(-1).try_to_unsigned_u256()
+        );
+
+        // The range of int256 is -2^255 to 2^255 - 1
+        let min = -BigInt::from(2).pow(255);
+        let max = BigInt::from(2).pow(255) - BigInt::from(1);
+        assert_eq!(Ok(U256::max_value()), BigInt::from(-1).try_to_signed_u256());
+        assert_eq!(Ok(U256::from(42)), BigInt::from(42).try_to_signed_u256());
+        assert_eq!(Ok(max.to_signed_u256()), max.try_to_signed_u256());
+        assert_eq!(Ok(min.to_signed_u256()), min.try_to_signed_u256());
+        assert_eq!(
+            Err(ArithmeticError::Overflow),
+            (max + BigInt::from(1)).try_to_signed_u256()
+        );
+        assert_eq!(
+            Err(ArithmeticError::Overflow),
+            (min - BigInt::from(1)).try_to_signed_u256()
+        );
+        assert_eq!(
+            Err(ArithmeticError::Overflow),
+            too_large.try_to_signed_u256()
+        );
+    }
+
+    #[test]
+    fn big_int_bits() {
+        let mut n = BigInt::from(1) << 100;
+        n.set_bit(3, true);
+        assert!(n.bit(100));
+        assert!(n.bit(3));
+        assert!(!n.bit(4));
+        assert!(!n.bit(99));
+        // Bits beyond the length of a positive value are not set
+        assert!(!n.bit(1000));
+
+        n.set_bit(100, false);
+        assert_eq!(BigInt::from(8), n);
+
+        // Setting a bit beyond the current length grows the value
+        n.set_bit(200, true);
+        assert_eq!(BigInt::from(2).pow(200) + BigInt::from(8), n);
+        n.set_bit(200, false);
+        n.set_bit(3, false);
+        assert_eq!(BigInt::from(0), n);
+        // Setting bit 7 must not make the value negative
+        n.set_bit(7, true);
+        assert_eq!(BigInt::from(128), n);
+
+        // Negative values use two's complement with infinite sign extension
+        let mut n = BigInt::from(-2);
+        assert!(!n.bit(0));
+        assert!(n.bit(1));
+        assert!(n.bit(1000));
+        n.set_bit(0, true);
+        assert_eq!(BigInt::from(-1), n);
+        n.set_bit(8, false);
+        assert_eq!(BigInt::from(-257), n);
+        n.set_bit(1000, true);
+        assert_eq!(BigInt::from(-257), n);
     }
 }
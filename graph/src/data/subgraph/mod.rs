@@ -1,6 +1,6 @@
 use ethabi::Contract;
 use failure;
-use failure::{err_msg, Error, SyncFailure};
+use failure::{err_msg, Error};
 use futures03::{
     future::{try_join, try_join3},
     stream::FuturesOrdered,
@@ -13,15 +13,18 @@ use serde::ser;
 use serde_yaml;
 use slog::{info, Logger};
 use stable_hash::prelude::*;
+use stable_hash::utils::{stable_hash_with_hasher, AsBytes};
+use twox_hash::XxHash64;
 use web3::types::{Address, H256};
 
 use crate::components::link_resolver::LinkResolver;
-use crate::components::store::{Store, StoreError, SubgraphDeploymentStore};
+use crate::components::store::{ChainStore, Store, StoreError, SubgraphDeploymentStore};
 use crate::components::subgraph::DataSourceTemplateInfo;
+use crate::data::graphql::ext::DocumentExt;
 use crate::data::graphql::{TryFromValue, ValueMap};
 use crate::data::query::QueryExecutionError;
 use crate::data::schema::{Schema, SchemaImportError, SchemaValidationError};
-use crate::data::store::Entity;
+use crate::data::store::{scalar::Bytes, Entity};
 use crate::data::subgraph::schema::{
     EthereumBlockHandlerEntity, EthereumCallHandlerEntity, EthereumContractAbiEntity,
     EthereumContractDataSourceTemplateEntity, EthereumContractDataSourceTemplateSourceEntity,
@@ -31,16 +34,35 @@ use crate::data::subgraph::schema::{
 use crate::prelude::{format_err, BlockNumber, Deserialize, Fail, Serialize};
 use crate::util::ethereum::string_to_h256;
 use graphql_parser::query as q;
+use lazy_static::lazy_static;
 
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::fmt;
 use std::ops::Deref;
 use std::str::FromStr;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 /// Rust representation of the GraphQL schema for a `SubgraphManifest`.
 pub mod schema;
 
+lazy_static! {
+    /// The latest mapping API version, i.e., the `apiVersion` of mappings,
+    /// that this node supports
+    pub static ref MAX_API_VERSION: semver::Version = semver::Version::new(0, 0, 4);
+
+    /// The latest manifest spec version, i.e., the `specVersion` of
+    /// subgraph manifests, that this node supports.
+    ///
+    /// Before spec versions were checked, there were already subgraphs in
+    /// the wild with spec version 0.0.3, due to confusion with the api
+    /// version. To avoid breaking those, we accept 0.0.3 though it
+    /// doesn't exist. In the future we should not use 0.0.3 as version
+    /// and skip to 0.0.4 to avoid ambiguity.
+    pub static ref MAX_SPEC_VERSION: semver::Version = semver::Version::new(0, 0, 3);
+}
+
 /// Deserialize an Address (with or without '0x' prefix).
 fn deserialize_address<'de, D>(deserializer: D) -> Result<Option<Address>, D::Error>
 where
@@ -343,16 +365,134 @@ pub enum SubgraphAssignmentProviderEvent {
     SubgraphStop(SubgraphDeploymentId),
 }
 
+/// The features that a subgraph can declare in the `features` section of
+/// its manifest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SubgraphFeature {
+    Grafting,
+    FullTextSearch,
+    NonFatalErrors,
+}
+
+impl FromStr for SubgraphFeature {
+    type Err = SubgraphManifestValidationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "grafting" => Ok(SubgraphFeature::Grafting),
+            "fullTextSearch" => Ok(SubgraphFeature::FullTextSearch),
+            "nonFatalErrors" => Ok(SubgraphFeature::NonFatalErrors),
+            _ => Err(SubgraphManifestValidationError::UnknownFeature(
+                s.to_owned(),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for SubgraphFeature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            SubgraphFeature::Grafting => "grafting",
+            SubgraphFeature::FullTextSearch => "fullTextSearch",
+            SubgraphFeature::NonFatalErrors => "nonFatalErrors",
+        };
+        f.write_str(name)
+    }
+}
+
+/// How much history of entities a subgraph needs, as declared with
+/// `indexerHints.prune` in its manifest. This is only a hint to indexers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PruneHint {
+    /// Keep all history
+    Never,
+    /// Let the indexer decide how much history to keep
+    Auto,
+    /// Keep the history of the given number of most recent blocks
+    Blocks(u64),
+}
+
+impl FromStr for PruneHint {
+    type Err = SubgraphManifestValidationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "never" => Ok(PruneHint::Never),
+            "auto" => Ok(PruneHint::Auto),
+            _ => u64::from_str(s)
+                .map(PruneHint::Blocks)
+                .map_err(|_| SubgraphManifestValidationError::InvalidPruneHint(s.to_owned())),
+        }
+    }
+}
+
+/// The `indexerHints` section of a manifest
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+pub struct IndexerHints {
+    /// Either `never`, `auto`, or a number of blocks; see `PruneHint`. This
+    /// is kept as a string so that `validate` can report invalid values
+    #[serde(default, deserialize_with = "deserialize_prune_hint")]
+    pub prune: Option<String>,
+}
+
+impl IndexerHints {
+    /// The parsed `prune` hint, or `None` if there is none or it is invalid
+    pub fn prune_hint(&self) -> Option<PruneHint> {
+        self.prune
+            .as_ref()
+            .and_then(|prune| PruneHint::from_str(prune).ok())
+    }
+}
+
+impl StableHash for IndexerHints {
+    fn stable_hash(&self, mut sequence_number: impl SequenceNumber, state: &mut impl StableHasher) {
+        self.prune.stable_hash(sequence_number.next_child(), state);
+    }
+}
+
+/// Deserialize the `prune` hint, which is either a string or a number of
+/// blocks, as a string.
+fn deserialize_prune_hint<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrNumber {
+        String(String),
+        Number(i64),
+    }
+
+    Ok(
+        Option::<StringOrNumber>::deserialize(deserializer)?.map(|prune| match prune {
+            StringOrNumber::String(s) => s,
+            StringOrNumber::Number(n) => n.to_string(),
+        }),
+    )
+}
+
 #[derive(Fail, Debug)]
 pub enum SubgraphManifestValidationWarning {
     #[fail(display = "schema validation produced warnings: {:?}", _0)]
     SchemaValidationWarning(SchemaImportError),
+    #[fail(display = "subgraph manifest has no description")]
+    MissingDescription,
+    #[fail(
+        display = "subgraph uses the feature `{}` without declaring it under `features`",
+        _0
+    )]
+    FeatureNotDeclared(SubgraphFeature),
+    #[fail(
+        display = "data source `{}` starts at block {}, but the chain head is only at block {}",
+        _0, _1, _2
+    )]
+    StartBlockInFuture(String, u64, u64), // (data source, start block, chain head)
 }
 
 #[derive(Fail, Debug)]
 pub enum SubgraphManifestValidationError {
-    #[fail(display = "subgraph has no data sources")]
-    NoDataSources,
+    #[fail(display = "subgraph has neither data sources nor templates")]
+    NoDataSourcesOrTemplates,
     #[fail(display = "subgraph source address is required")]
     SourceAddressRequired,
     #[fail(display = "subgraph cannot index data from different Ethereum networks")]
@@ -369,6 +509,50 @@ pub enum SubgraphManifestValidationError {
     SchemaValidationError(Vec<SchemaValidationError>),
     #[fail(display = "the graft base is invalid: {}", _0)]
     GraftBaseInvalid(String),
+    #[fail(
+        display = "manifest spec version `{}` is not supported, this Graph Node only supports \
+                   manifest spec versions <= {}",
+        _0, _1
+    )]
+    UnsupportedSpecVersion(String, semver::Version), // (spec version, `MAX_SPEC_VERSION`)
+    #[fail(display = "schema file `{}` could not be resolved: {}", _0, _1)]
+    SchemaUnresolvable(String, String), // (link, error)
+    /// Reported while resolving the manifest, see `UnresolvedMappingABI::resolve`
+    #[fail(display = "ABI `{}` is invalid: {}", name, reason)]
+    AbiInvalid { name: String, reason: String },
+    #[fail(display = "network `{}` is not supported by this Graph Node", _0)]
+    NetworkNotSupported(String),
+    #[fail(display = "the schema does not define any types with an @entity directive")]
+    SchemaHasNoEntities,
+    #[fail(display = "feature `{}` is not known to this Graph Node", _0)]
+    UnknownFeature(String),
+    #[fail(
+        display = "the mapping of data source `{}` lists entity `{}`, but the schema has no \
+                   @entity type with that name",
+        data_source, entity
+    )]
+    MappingEntityNotInSchema { data_source: String, entity: String },
+    #[fail(
+        display = "the mapping of data source `{}` uses API version `{}`, which is not supported",
+        data_source, version
+    )]
+    UnsupportedApiVersion {
+        data_source: String,
+        version: String,
+    },
+    #[fail(
+        display = "`indexerHints.prune` must be `never`, `auto`, or a number of blocks, but is `{}`",
+        _0
+    )]
+    InvalidPruneHint(String),
+    #[fail(
+        display = "data source `{}` uses ABI `{}`, but does not list it among the ABIs of its \
+                   mapping",
+        data_source, name
+    )]
+    AbiNameNotDeclared { data_source: String, name: String },
+    #[fail(display = "the grafts form a cycle: {}", _0)]
+    GraftCycle(String),
 }
 
 #[derive(Fail, Debug)]
@@ -379,6 +563,8 @@ pub enum SubgraphManifestResolveError {
     NonUtf8,
     #[fail(display = "subgraph is not valid YAML")]
     InvalidFormat,
+    #[fail(display = "unknown field `{}` in subgraph manifest", _0)]
+    UnknownField(String),
     #[fail(display = "resolve error: {}", _0)]
     ResolveError(failure::Error),
 }
@@ -399,6 +585,12 @@ pub struct Link {
     pub link: String,
 }
 
+impl StableHash for Link {
+    fn stable_hash(&self, mut sequence_number: impl SequenceNumber, state: &mut impl StableHasher) {
+        self.link.stable_hash(sequence_number.next_child(), state);
+    }
+}
+
 impl From<String> for Link {
     fn from(s: String) -> Self {
         Self { link: s }
@@ -419,7 +611,12 @@ impl UnresolvedSchema {
     ) -> Result<Schema, failure::Error> {
         info!(logger, "Resolve schema"; "link" => &self.file.link);
 
-        let schema_bytes = resolver.cat(&logger, &self.file).await?;
+        let schema_bytes = resolver.cat(&logger, &self.file).await.map_err(|e| {
+            SubgraphManifestValidationError::SchemaUnresolvable(
+                self.file.link.clone(),
+                e.to_string(),
+            )
+        })?;
         Schema::parse(&String::from_utf8(schema_bytes)?, id)
     }
 }
@@ -433,6 +630,18 @@ pub struct Source {
     pub start_block: u64,
 }
 
+impl StableHash for Source {
+    fn stable_hash(&self, mut sequence_number: impl SequenceNumber, state: &mut impl StableHasher) {
+        self.address
+            .as_ref()
+            .map(|address| AsBytes(address.as_bytes()))
+            .stable_hash(sequence_number.next_child(), state);
+        self.abi.stable_hash(sequence_number.next_child(), state);
+        self.start_block
+            .stable_hash(sequence_number.next_child(), state);
+    }
+}
+
 impl From<EthereumContractSourceEntity> for Source {
     fn from(entity: EthereumContractSourceEntity) -> Self {
         Self {
@@ -448,6 +657,12 @@ pub struct TemplateSource {
     pub abi: String,
 }
 
+impl StableHash for TemplateSource {
+    fn stable_hash(&self, mut sequence_number: impl SequenceNumber, state: &mut impl StableHasher) {
+        self.abi.stable_hash(sequence_number.next_child(), state);
+    }
+}
+
 impl From<EthereumContractDataSourceTemplateSourceEntity> for TemplateSource {
     fn from(entity: EthereumContractDataSourceTemplateSourceEntity) -> Self {
         Self { abi: entity.abi }
@@ -476,7 +691,19 @@ pub struct MappingABI {
     pub link: Link,
 }
 
+/// The contract itself is identified by its link
+impl StableHash for MappingABI {
+    fn stable_hash(&self, mut sequence_number: impl SequenceNumber, state: &mut impl StableHasher) {
+        self.name.stable_hash(sequence_number.next_child(), state);
+        self.link.stable_hash(sequence_number.next_child(), state);
+    }
+}
+
 impl UnresolvedMappingABI {
+    /// Fetch and parse the ABI. An ABI that is not valid JSON or not a valid
+    /// contract ABI fails with `SubgraphManifestValidationError::AbiInvalid`;
+    /// this is the only place that checks that, since `MappingABI` can only
+    /// hold a parsed `Contract`.
     pub async fn resolve(
         self,
         resolver: &impl LinkResolver,
@@ -490,7 +717,12 @@ impl UnresolvedMappingABI {
         );
 
         let contract_bytes = resolver.cat(&logger, &self.file).await?;
-        let contract = Contract::load(&*contract_bytes).map_err(SyncFailure::new)?;
+        let contract = Contract::load(&*contract_bytes).map_err(|e| {
+            SubgraphManifestValidationError::AbiInvalid {
+                name: self.name.clone(),
+                reason: e.to_string(),
+            }
+        })?;
         Ok(MappingABI {
             name: self.name,
             contract,
@@ -505,6 +737,19 @@ pub struct MappingBlockHandler {
     pub filter: Option<BlockHandlerFilter>,
 }
 
+impl StableHash for MappingBlockHandler {
+    fn stable_hash(&self, mut sequence_number: impl SequenceNumber, state: &mut impl StableHasher) {
+        self.handler
+            .stable_hash(sequence_number.next_child(), state);
+        self.filter
+            .as_ref()
+            .map(|filter| match filter {
+                BlockHandlerFilter::Call => "call".to_owned(),
+            })
+            .stable_hash(sequence_number.next_child(), state);
+    }
+}
+
 #[derive(Clone, Debug, Hash, Eq, PartialEq, Deserialize)]
 #[serde(tag = "kind", rename_all = "lowercase")]
 pub enum BlockHandlerFilter {
@@ -528,6 +773,15 @@ pub struct MappingCallHandler {
     pub handler: String,
 }
 
+impl StableHash for MappingCallHandler {
+    fn stable_hash(&self, mut sequence_number: impl SequenceNumber, state: &mut impl StableHasher) {
+        self.function
+            .stable_hash(sequence_number.next_child(), state);
+        self.handler
+            .stable_hash(sequence_number.next_child(), state);
+    }
+}
+
 impl From<EthereumCallHandlerEntity> for MappingCallHandler {
     fn from(entity: EthereumCallHandlerEntity) -> Self {
         Self {
@@ -544,6 +798,18 @@ pub struct MappingEventHandler {
     pub handler: String,
 }
 
+impl StableHash for MappingEventHandler {
+    fn stable_hash(&self, mut sequence_number: impl SequenceNumber, state: &mut impl StableHasher) {
+        self.event.stable_hash(sequence_number.next_child(), state);
+        self.topic0
+            .as_ref()
+            .map(|topic0| AsBytes(topic0.as_bytes()))
+            .stable_hash(sequence_number.next_child(), state);
+        self.handler
+            .stable_hash(sequence_number.next_child(), state);
+    }
+}
+
 impl MappingEventHandler {
     pub fn topic0(&self) -> H256 {
         self.topic0
@@ -592,6 +858,27 @@ pub struct Mapping {
     pub link: Link,
 }
 
+/// The WASM module is identified by its link
+impl StableHash for Mapping {
+    fn stable_hash(&self, mut sequence_number: impl SequenceNumber, state: &mut impl StableHasher) {
+        self.kind.stable_hash(sequence_number.next_child(), state);
+        self.api_version
+            .stable_hash(sequence_number.next_child(), state);
+        self.language
+            .stable_hash(sequence_number.next_child(), state);
+        self.entities
+            .stable_hash(sequence_number.next_child(), state);
+        self.abis.stable_hash(sequence_number.next_child(), state);
+        self.block_handlers
+            .stable_hash(sequence_number.next_child(), state);
+        self.call_handlers
+            .stable_hash(sequence_number.next_child(), state);
+        self.event_handlers
+            .stable_hash(sequence_number.next_child(), state);
+        self.link.stable_hash(sequence_number.next_child(), state);
+    }
+}
+
 impl UnresolvedMapping {
     pub async fn resolve(
         self,
@@ -669,6 +956,22 @@ pub struct BaseDataSource<M, T> {
     pub templates: Vec<T>, // Deprecated in manifest spec version 0.0.2
 }
 
+impl<M: StableHash, T: StableHash> StableHash for BaseDataSource<M, T> {
+    fn stable_hash(&self, mut sequence_number: impl SequenceNumber, state: &mut impl StableHasher) {
+        self.kind.stable_hash(sequence_number.next_child(), state);
+        self.network
+            .stable_hash(sequence_number.next_child(), state);
+        self.name.stable_hash(sequence_number.next_child(), state);
+        self.source.stable_hash(sequence_number.next_child(), state);
+        self.mapping
+            .stable_hash(sequence_number.next_child(), state);
+        self.context
+            .stable_hash(sequence_number.next_child(), state);
+        self.templates
+            .stable_hash(sequence_number.next_child(), state);
+    }
+}
+
 pub type UnresolvedDataSource = BaseDataSource<UnresolvedMapping, UnresolvedDataSourceTemplate>;
 pub type DataSource = BaseDataSource<Mapping, DataSourceTemplate>;
 
@@ -795,6 +1098,18 @@ pub struct BaseDataSourceTemplate<M> {
     pub mapping: M,
 }
 
+impl<M: StableHash> StableHash for BaseDataSourceTemplate<M> {
+    fn stable_hash(&self, mut sequence_number: impl SequenceNumber, state: &mut impl StableHasher) {
+        self.kind.stable_hash(sequence_number.next_child(), state);
+        self.network
+            .stable_hash(sequence_number.next_child(), state);
+        self.name.stable_hash(sequence_number.next_child(), state);
+        self.source.stable_hash(sequence_number.next_child(), state);
+        self.mapping
+            .stable_hash(sequence_number.next_child(), state);
+    }
+}
+
 impl From<EthereumContractDataSourceTemplateEntity> for UnresolvedDataSourceTemplate {
     fn from(entity: EthereumContractDataSourceTemplateEntity) -> Self {
         Self {
@@ -855,11 +1170,80 @@ impl DataSourceTemplate {
 #[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Graft {
-    pub base: SubgraphDeploymentId,
+    /// The deployment id of the base, exactly as it appears in the manifest;
+    /// use `base_id` to get it as a `SubgraphDeploymentId`
+    pub base: String,
     pub block: BlockNumber,
+    /// The grafts leading up to this one, oldest first. This is only
+    /// non-empty if the manifest lists a chain of grafts, in which case
+    /// the last graft in the list is the one the subgraph is grafted onto
+    #[serde(skip)]
+    pub previous: Vec<Graft>,
 }
 
+impl StableHash for Graft {
+    fn stable_hash(&self, mut sequence_number: impl SequenceNumber, state: &mut impl StableHasher) {
+        // Hash `base` the same way as a `SubgraphDeploymentId` so that
+        // content hashes do not change
+        let mut base_sequence_number = sequence_number.next_child();
+        self.base
+            .stable_hash(base_sequence_number.next_child(), state);
+        self.block.stable_hash(sequence_number.next_child(), state);
+        self.previous
+            .stable_hash(sequence_number.next_child(), state);
+    }
+}
+
+/// Deserialize the `graft` of a manifest, which is either a single graft or
+/// a non-empty list of grafts.
+fn deserialize_graft<'de, D>(deserializer: D) -> Result<Option<Graft>, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    use serde::de::Error;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum GraftOrChain {
+        Graft(Graft),
+        Chain(Vec<Graft>),
+    }
+
+    match Option::<GraftOrChain>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(GraftOrChain::Graft(graft)) => Ok(Some(graft)),
+        Some(GraftOrChain::Chain(mut chain)) => {
+            let mut graft = chain
+                .pop()
+                .ok_or_else(|| D::Error::custom("the list of grafts must not be empty"))?;
+            graft.previous = chain;
+            Ok(Some(graft))
+        }
+    }
+}
+
+/// A graft block that is more than this many blocks beyond the latest block
+/// of the base is most likely a mistake in the manifest, rather than a sign
+/// that the base has not caught up yet.
+const GRAFT_BLOCK_MAX_LEAD: u64 = 1_000_000;
+
 impl Graft {
+    /// All grafts in the chain that ends with this graft, oldest first
+    pub fn chain(&self) -> impl Iterator<Item = &Graft> {
+        self.previous.iter().chain(std::iter::once(self))
+    }
+
+    /// The deployment id of the base, or a `GraftBaseInvalid` error if
+    /// `base` is not a valid deployment id
+    pub fn base_id(&self) -> Result<SubgraphDeploymentId, SubgraphManifestValidationError> {
+        SubgraphDeploymentId::new(self.base.as_str()).map_err(|()| {
+            SubgraphManifestValidationError::GraftBaseInvalid(format!(
+                "graft base `{}` is not a valid deployment id",
+                self.base
+            ))
+        })
+    }
+
     fn validate<S: Store + SubgraphDeploymentStore>(
         &self,
         store: Arc<S>,
@@ -868,23 +1252,64 @@ impl Graft {
             vec![SubgraphManifestValidationError::GraftBaseInvalid(msg)]
         }
 
-        match store.block_ptr(self.base.clone()) {
-            Err(e) => gbi(e.to_string()),
+        // Grafting onto block 0 would not copy anything from the base
+        if self.block <= 0 {
+            return gbi("graft block must be greater than 0".to_owned());
+        }
+
+        // Check the format of the base before asking the store about it
+        let base = match self.base_id() {
+            Ok(base) => base,
+            Err(e) => return vec![e],
+        };
+
+        let block = self.block as u64;
+        match store.block_ptr(base) {
+            Err(e) => gbi(format!(
+                "failed to graft onto `{}` at block {}: {}",
+                self.base, block, e
+            )),
             Ok(None) => gbi(format!(
-                "failed to graft onto `{}` since it has not processed any blocks",
-                self.base
+                "failed to graft onto `{}` at block {} since it has not processed any blocks yet",
+                self.base, block
             )),
-            Ok(Some(ptr)) => {
-                if ptr.number < self.block as u64 {
-                    gbi(format!(
-                        "failed to graft onto `{}` at block {} since it has only processed block {}",
-                        self.base, self.block, ptr.number
-                    ))
-                } else {
-                    vec![]
-                }
+            Ok(Some(ptr)) if block > ptr.number + GRAFT_BLOCK_MAX_LEAD => gbi(format!(
+                "failed to graft onto `{}` at block {} since that is more than {} blocks beyond \
+                 block {}, the latest block it has processed; the graft block is most likely wrong",
+                self.base, block, GRAFT_BLOCK_MAX_LEAD, ptr.number
+            )),
+            Ok(Some(ptr)) if block > ptr.number => gbi(format!(
+                "failed to graft onto `{}` at block {} since it has only processed block {} so far",
+                self.base, block, ptr.number
+            )),
+            Ok(Some(_)) => vec![],
+        }
+    }
+
+    /// Follow the graft bases, starting with the base of this graft, through
+    /// the store and return a `GraftCycle` error if they lead back to a
+    /// deployment that was already visited, starting with `id`, the
+    /// deployment that is being grafted. The walk stops at a base the store
+    /// can not tell us about; `validate` reports such bases.
+    fn validate_acyclic<S: SubgraphDeploymentStore>(
+        &self,
+        id: &SubgraphDeploymentId,
+        store: &S,
+    ) -> Option<SubgraphManifestValidationError> {
+        let mut seen = vec![id.clone()];
+        let mut base = self.base_id().ok();
+        while let Some(current) = base {
+            let cycle = seen.contains(&current);
+            seen.push(current.clone());
+            if cycle {
+                let path: Vec<_> = seen.iter().map(|id| id.to_string()).collect();
+                return Some(SubgraphManifestValidationError::GraftCycle(
+                    path.join(" -> "),
+                ));
             }
+            base = store.graft_base(&current).ok().flatten();
         }
+        None
     }
 }
 
@@ -898,10 +1323,31 @@ pub struct BaseSubgraphManifest<S, D, T> {
     pub repository: Option<String>,
     pub schema: S,
     pub data_sources: Vec<D>,
+    #[serde(default, deserialize_with = "deserialize_graft")]
     pub graft: Option<Graft>,
     #[serde(default)]
     pub templates: Vec<T>,
-}
+    #[serde(default)]
+    pub features: Vec<String>,
+    #[serde(default)]
+    pub indexer_hints: Option<IndexerHints>,
+}
+
+/// The top-level keys a manifest may contain; must be kept in sync with the
+/// fields of `BaseSubgraphManifest`.
+const MANIFEST_FIELDS: &[&str] = &[
+    "id",
+    "location",
+    "specVersion",
+    "description",
+    "repository",
+    "schema",
+    "dataSources",
+    "graft",
+    "templates",
+    "features",
+    "indexerHints",
+];
 
 /// Consider two subgraphs to be equal if they come from the same IPLD link.
 impl<S, D, T> PartialEq for BaseSubgraphManifest<S, D, T> {
@@ -934,24 +1380,37 @@ impl UnvalidatedSubgraphManifest {
         ))
     }
 
-    pub fn validate<S: Store + SubgraphDeploymentStore>(
+    /// Validate the manifest. The networks the manifest uses must be among
+    /// those in `chain_stores`. Data source start blocks are also checked
+    /// against the chain head of their network; since the chain head may
+    /// still be catching up, starting after it is only a warning.
+    pub fn validate<S: Store + SubgraphDeploymentStore, C: ChainStore>(
         self,
         store: Arc<S>,
+        chain_stores: &HashMap<String, Arc<C>>,
     ) -> Result<
         (SubgraphManifest, Vec<SubgraphManifestValidationWarning>),
         Vec<SubgraphManifestValidationError>,
     > {
         let (schemas, import_errors) = self.0.schema.resolve_schema_references(store.clone());
-        let validation_warnings = import_errors
+        let mut validation_warnings: Vec<_> = import_errors
             .into_iter()
             .map(SubgraphManifestValidationWarning::SchemaValidationWarning)
             .collect();
 
+        // Warn about a missing description; it is optional, but it is what
+        // users see when they browse subgraphs
+        if self.0.description.is_none() {
+            validation_warnings.push(SubgraphManifestValidationWarning::MissingDescription);
+        }
+
         let mut errors: Vec<SubgraphManifestValidationError> = vec![];
 
-        // Validate that the manifest has at least one data source
-        if self.0.data_sources.is_empty() {
-            errors.push(SubgraphManifestValidationError::NoDataSources);
+        // Validate that the manifest has at least one data source or
+        // template; a manifest without either is most likely empty by
+        // accident. Manifests with only templates are allowed
+        if self.0.data_sources.is_empty() && self.0.templates.is_empty() {
+            errors.push(SubgraphManifestValidationError::NoDataSourcesOrTemplates);
         }
 
         // Validate that the manifest has a `source` address in each data source
@@ -966,6 +1425,30 @@ impl UnvalidatedSubgraphManifest {
             errors.push(SubgraphManifestValidationError::SourceAddressRequired)
         };
 
+        // Validate that the ABI of each data source's and template's `source`
+        // is one of the ABIs of its mapping. Mapping handlers refer to events
+        // and functions, not to ABIs, so `source.abi` is the only place where
+        // they name an ABI
+        let sources = self
+            .0
+            .data_sources
+            .iter()
+            .map(|d| (&d.name, &d.source.abi, &d.mapping))
+            .chain(
+                self.0
+                    .templates
+                    .iter()
+                    .map(|t| (&t.name, &t.source.abi, &t.mapping)),
+            );
+        for (data_source, source_abi, mapping) in sources {
+            if !mapping.abis.iter().any(|abi| &abi.name == source_abi) {
+                errors.push(SubgraphManifestValidationError::AbiNameNotDeclared {
+                    data_source: data_source.clone(),
+                    name: source_abi.clone(),
+                });
+            }
+        }
+
         // Validate that there are no more than one of each type of
         // block_handler in each data source.
         let has_too_many_block_handlers = self.0.data_sources.iter().any(|data_source| {
@@ -996,18 +1479,77 @@ impl UnvalidatedSubgraphManifest {
             .0
             .data_sources
             .iter()
-            .cloned()
-            .filter(|d| d.kind.eq("ethereum/contract"))
-            .filter_map(|d| d.network)
+            .map(|d| (&d.kind, &d.network))
+            .chain(self.0.templates.iter().map(|t| (&t.kind, &t.network)))
+            .filter(|(kind, _)| kind.as_str() == "ethereum/contract")
+            .filter_map(|(_, network)| network.clone())
             .collect::<Vec<String>>();
         networks.sort();
         networks.dedup();
+
+        // Validate that this node has a store for each of the networks; a
+        // typo in the network name would otherwise only show up later when
+        // looking for an Ethereum adapter
+        for network in &networks {
+            if !chain_stores.contains_key(network) {
+                errors.push(SubgraphManifestValidationError::NetworkNotSupported(
+                    network.clone(),
+                ));
+            }
+        }
+
         match networks.len() {
             0 => errors.push(SubgraphManifestValidationError::EthereumNetworkRequired),
-            1 => (),
+            1 => validation_warnings.extend(self.validate_start_blocks(&networks[0], chain_stores)),
             _ => errors.push(SubgraphManifestValidationError::MultipleEthereumNetworks),
         }
 
+        // Validate that the schema defines at least one entity type; without
+        // one, the subgraph could never store anything
+        let entity_types: Vec<&str> = self
+            .0
+            .schema
+            .document
+            .get_object_type_definitions()
+            .into_iter()
+            .filter(|t| t.directives.iter().any(|d| d.name == "entity"))
+            .map(|t| t.name.as_str())
+            .collect();
+        if entity_types.is_empty() {
+            errors.push(SubgraphManifestValidationError::SchemaHasNoEntities);
+        }
+
+        // Validate that the entities that mappings list are entity types in
+        // the schema
+        let mappings = self
+            .0
+            .data_sources
+            .iter()
+            .map(|d| (&d.name, &d.mapping))
+            .chain(self.0.templates.iter().map(|t| (&t.name, &t.mapping)));
+        for (data_source, mapping) in mappings.clone() {
+            for entity in &mapping.entities {
+                if !entity_types.contains(&entity.as_str()) {
+                    errors.push(SubgraphManifestValidationError::MappingEntityNotInSchema {
+                        data_source: data_source.clone(),
+                        entity: entity.clone(),
+                    });
+                }
+            }
+        }
+
+        // Validate that this node supports the API version of each mapping;
+        // otherwise, the mapping would fail when it is instantiated
+        for (data_source, mapping) in mappings {
+            match semver::Version::parse(&mapping.api_version) {
+                Ok(ref version) if *version <= *MAX_API_VERSION => {}
+                _ => errors.push(SubgraphManifestValidationError::UnsupportedApiVersion {
+                    data_source: data_source.clone(),
+                    version: mapping.api_version.clone(),
+                }),
+            }
+        }
+
         self.0
             .schema
             .validate(&schemas)
@@ -1020,7 +1562,32 @@ impl UnvalidatedSubgraphManifest {
             });
 
         if let Some(graft) = &self.0.graft {
-            errors.extend(graft.validate(store));
+            for graft in graft.chain() {
+                errors.extend(graft.validate(store.clone()));
+            }
+            errors.extend(graft.validate_acyclic(&self.0.id, store.as_ref()));
+        }
+
+        // Validate the declared features. For backwards compatibility, using
+        // a feature without declaring it is only a warning
+        let mut features = vec![];
+        for feature in &self.0.features {
+            match SubgraphFeature::from_str(feature) {
+                Ok(feature) => features.push(feature),
+                Err(e) => errors.push(e),
+            }
+        }
+        if self.0.graft.is_some() && !features.contains(&SubgraphFeature::Grafting) {
+            validation_warnings.push(SubgraphManifestValidationWarning::FeatureNotDeclared(
+                SubgraphFeature::Grafting,
+            ));
+        }
+
+        // Validate the indexer hints
+        if let Some(prune) = self.0.indexer_hints.as_ref().and_then(|h| h.prune.as_ref()) {
+            if let Err(e) = PruneHint::from_str(prune) {
+                errors.push(e);
+            }
         }
 
         match errors.is_empty() {
@@ -1028,9 +1595,75 @@ impl UnvalidatedSubgraphManifest {
             false => Err(errors),
         }
     }
+
+    /// Check that no data source on `network` starts after the current chain
+    /// head. The check is skipped if we don't know the chain head.
+    fn validate_start_blocks<C: ChainStore>(
+        &self,
+        network: &str,
+        chain_stores: &HashMap<String, Arc<C>>,
+    ) -> Vec<SubgraphManifestValidationWarning> {
+        let head = match chain_stores
+            .get(network)
+            .map(|store| store.chain_head_ptr())
+        {
+            Some(Ok(Some(head))) => head,
+            _ => return vec![],
+        };
+
+        self.0
+            .data_sources
+            .iter()
+            .filter(|d| d.network.as_ref().map(String::as_str) == Some(network))
+            .filter(|d| d.source.start_block > head.number)
+            .map(|d| {
+                SubgraphManifestValidationWarning::StartBlockInFuture(
+                    d.name.clone(),
+                    d.source.start_block,
+                    head.number,
+                )
+            })
+            .collect()
+    }
+}
+
+impl StableHash for SubgraphManifest {
+    fn stable_hash(&self, mut sequence_number: impl SequenceNumber, state: &mut impl StableHasher) {
+        self.id.stable_hash(sequence_number.next_child(), state);
+        self.location
+            .stable_hash(sequence_number.next_child(), state);
+        self.spec_version
+            .stable_hash(sequence_number.next_child(), state);
+        self.description
+            .stable_hash(sequence_number.next_child(), state);
+        self.repository
+            .stable_hash(sequence_number.next_child(), state);
+        self.schema
+            .document
+            .to_string()
+            .stable_hash(sequence_number.next_child(), state);
+        self.data_sources
+            .stable_hash(sequence_number.next_child(), state);
+        self.graft.stable_hash(sequence_number.next_child(), state);
+        self.templates
+            .stable_hash(sequence_number.next_child(), state);
+        self.features
+            .stable_hash(sequence_number.next_child(), state);
+        self.indexer_hints
+            .stable_hash(sequence_number.next_child(), state);
+    }
 }
 
 impl SubgraphManifest {
+    /// A deterministic hash of the resolved manifest that can be used to
+    /// check that two nodes resolved the same manifest. The schema is hashed
+    /// by its contents, while ABIs and mappings are identified by their
+    /// links.
+    pub fn content_hash(&self) -> Bytes {
+        let hash = stable_hash_with_hasher::<XxHash64, _>(self);
+        Bytes::from(hash.to_be_bytes().to_vec())
+    }
+
     /// Entry point for resolving a subgraph definition.
     /// Right now the only supported links are of the form:
     /// `/ipfs/QmUmg7BZC1YP1ca66rRtWKxpXp77WgVHrnv263JtDuvs2k`
@@ -1041,6 +1674,53 @@ impl SubgraphManifest {
     ) -> Result<Self, SubgraphManifestResolveError> {
         info!(logger, "Resolve manifest"; "link" => &link.link);
 
+        let unresolved = Self::parse(link, resolver, logger, false).await?;
+
+        unresolved
+            .resolve(&*resolver, logger)
+            .await
+            .map_err(SubgraphManifestResolveError::ResolveError)
+    }
+
+    /// Like `resolve`, but fails with `UnknownField` if the manifest has a
+    /// top-level key that is not part of the manifest format, e.g. a
+    /// misspelled `datasources`, instead of silently ignoring it.
+    pub async fn resolve_strict(
+        link: Link,
+        resolver: &impl LinkResolver,
+        logger: &Logger,
+    ) -> Result<Self, SubgraphManifestResolveError> {
+        info!(logger, "Resolve manifest strictly"; "link" => &link.link);
+
+        let unresolved = Self::parse(link, resolver, logger, true).await?;
+
+        unresolved
+            .resolve(&*resolver, logger)
+            .await
+            .map_err(SubgraphManifestResolveError::ResolveError)
+    }
+
+    /// Lists all the links that resolving the manifest at `link` would
+    /// fetch: the schema, the ABIs and mappings of data sources and
+    /// templates, in the order in which they appear in the manifest and
+    /// without duplicates. Only the manifest itself is fetched.
+    pub async fn list_links(
+        link: Link,
+        resolver: &impl LinkResolver,
+        logger: &Logger,
+    ) -> Result<Vec<Link>, SubgraphManifestResolveError> {
+        Ok(Self::parse(link, resolver, logger, false).await?.links())
+    }
+
+    /// Fetches the manifest at `link` and parses it without resolving any
+    /// of the links in it. If `strict` is set, unknown top-level keys are
+    /// rejected.
+    async fn parse(
+        link: Link,
+        resolver: &impl LinkResolver,
+        logger: &Logger,
+        strict: bool,
+    ) -> Result<UnresolvedSubgraphManifest, SubgraphManifestResolveError> {
         let file_bytes = resolver
             .cat(logger, &link)
             .await
@@ -1054,6 +1734,17 @@ impl SubgraphManifest {
             .as_mapping_mut()
             .ok_or(SubgraphManifestResolveError::InvalidFormat)?;
 
+        if strict {
+            let unknown = raw_mapping.iter().find_map(|(key, _)| match key.as_str() {
+                Some(key) if MANIFEST_FIELDS.contains(&key) => None,
+                Some(key) => Some(key.to_owned()),
+                None => Some(format!("{:?}", key)),
+            });
+            if let Some(key) = unknown {
+                return Err(SubgraphManifestResolveError::UnknownField(key));
+            }
+        }
+
         // Inject the IPFS hash as the ID of the subgraph
         // into the definition.
         raw_mapping.insert(
@@ -1069,23 +1760,21 @@ impl SubgraphManifest {
         );
 
         // Parse the YAML data into an UnresolvedSubgraphManifest
-        let unresolved: UnresolvedSubgraphManifest = serde_yaml::from_value(raw)?;
-
-        unresolved
-            .resolve(&*resolver, logger)
-            .await
-            .map_err(SubgraphManifestResolveError::ResolveError)
+        Ok(serde_yaml::from_value(raw)?)
     }
 
     pub fn network_name(&self) -> String {
         // Assume the manifest has been validated, ensuring network names are homogenous
         self.data_sources
             .iter()
-            .cloned()
-            .filter(|d| &d.kind == "ethereum/contract")
-            .filter_map(|d| d.network)
+            .map(|d| (&d.kind, &d.network))
+            .chain(self.templates.iter().map(|t| (&t.kind, &t.network)))
+            .filter(|(kind, _)| kind.as_str() == "ethereum/contract")
+            .filter_map(|(_, network)| network.clone())
             .next()
-            .expect("Validated manifest does not have a network defined on any datasource")
+            .expect(
+                "Validated manifest does not have a network defined on any datasource or template",
+            )
     }
 
     pub fn start_blocks(&self) -> Vec<u64> {
@@ -1096,7 +1785,95 @@ impl SubgraphManifest {
     }
 }
 
+/// A cache of resolved manifests, keyed by the link of the manifest. Since
+/// `/ipfs/` links are content-addressed, the manifest behind a link never
+/// changes, and entries can be kept for a long time; they are dropped once
+/// they are older than the `ttl` of the cache. Concurrent requests for a
+/// manifest that is not cached yet may all resolve it.
+pub struct ManifestCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, (Instant, SubgraphManifest)>>,
+}
+
+impl ManifestCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Like `SubgraphManifest::resolve`, but returns the cached manifest if
+    /// `link` was resolved less than `ttl` ago. Errors are not cached. Links
+    /// of the form `/ipfs/ipfs_hash` and just `ipfs_hash` share an entry.
+    pub async fn resolve(
+        &self,
+        link: Link,
+        resolver: &impl LinkResolver,
+        logger: &Logger,
+    ) -> Result<SubgraphManifest, SubgraphManifestResolveError> {
+        // Discard the `/ipfs/` prefix (if present) to get the hash.
+        let key = link.link.trim_start_matches("/ipfs/").to_owned();
+        if let Some(manifest) = self.get(&key) {
+            return Ok(manifest);
+        }
+
+        let manifest = SubgraphManifest::resolve(link, resolver, logger).await?;
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key, (Instant::now(), manifest.clone()));
+        Ok(manifest)
+    }
+
+    /// Remove all entries from the cache
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    fn get(&self, link: &str) -> Option<SubgraphManifest> {
+        let ttl = self.ttl;
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, (added, _)| added.elapsed() < ttl);
+        entries.get(link).map(|(_, manifest)| manifest.clone())
+    }
+}
+
 impl UnresolvedSubgraphManifest {
+    fn links(&self) -> Vec<Link> {
+        fn mapping_links<'a>(mapping: &'a UnresolvedMapping) -> impl Iterator<Item = &'a Link> {
+            mapping
+                .abis
+                .iter()
+                .map(|abi| &abi.file)
+                .chain(std::iter::once(&mapping.file))
+        }
+
+        let data_sources = self.data_sources.iter().flat_map(|data_source| {
+            mapping_links(&data_source.mapping).chain(
+                data_source
+                    .templates
+                    .iter()
+                    .flat_map(|template| mapping_links(&template.mapping)),
+            )
+        });
+        let templates = self
+            .templates
+            .iter()
+            .flat_map(|template| mapping_links(&template.mapping));
+
+        let mut links: Vec<Link> = vec![];
+        for link in std::iter::once(&self.schema.file)
+            .chain(data_sources)
+            .chain(templates)
+        {
+            if !links.contains(link) {
+                links.push(link.clone());
+            }
+        }
+        links
+    }
+
     pub async fn resolve(
         self,
         resolver: &impl LinkResolver,
@@ -1112,22 +1889,18 @@ impl UnresolvedSubgraphManifest {
             data_sources,
             graft,
             templates,
+            features,
+            indexer_hints,
         } = self;
 
         match semver::Version::parse(&spec_version) {
-            // Before this check was introduced, there were already subgraphs in
-            // the wild with spec version 0.0.3, due to confusion with the api
-            // version. To avoid breaking those, we accept 0.0.3 though it
-            // doesn't exist. In the future we should not use 0.0.3 as version
-            // and skip to 0.0.4 to avoid ambiguity.
-            Ok(ref ver) if *ver <= semver::Version::new(0, 0, 3) => {}
+            Ok(ref ver) if *ver <= *MAX_SPEC_VERSION => {}
             _ => {
-                return Err(format_err!(
-                    "This Graph Node only supports manifest spec versions <= 0.0.2,
-                    but subgraph `{}` uses `{}`",
-                    id,
-                    spec_version
-                ));
+                return Err(SubgraphManifestValidationError::UnsupportedSpecVersion(
+                    spec_version,
+                    MAX_SPEC_VERSION.clone(),
+                )
+                .into());
             }
         }
 
@@ -1156,6 +1929,8 @@ impl UnresolvedSubgraphManifest {
             data_sources,
             graft,
             templates,
+            features,
+            indexer_hints,
         })
     }
 }
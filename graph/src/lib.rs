@@ -85,7 +85,11 @@ pub mod prelude {
     pub use crate::components::graphql::{
         GraphQlRunner, QueryResultFuture, SubscriptionResultFuture,
     };
-    pub use crate::components::link_resolver::{JsonStreamValue, JsonValueStream, LinkResolver};
+    pub use crate::components::link_resolver::{
+        ArweaveLinkResolver, CachingLinkResolver, ContentHashMismatch, FileLinkResolver,
+        FileSizeLimitExceeded, JsonStreamValue, JsonValueStream, LinkRecording, LinkResolver,
+        LinkTimeout, RecordingLinkResolver, ReplayLinkResolver, VerifyingLinkResolver,
+    };
     pub use crate::components::metrics::{
         aggregate::Aggregate, stopwatch::StopwatchMetrics, Collector, Counter, CounterVec, Gauge,
         GaugeVec, Histogram, HistogramOpts, HistogramVec, MetricsRegistry, Opts, PrometheusError,
@@ -118,18 +122,19 @@ pub mod prelude {
     };
     pub use crate::data::schema::Schema;
     pub use crate::data::store::ethereum::*;
-    pub use crate::data::store::scalar::{BigDecimal, BigInt, BigIntSign};
+    pub use crate::data::store::scalar::{BigDecimal, BigInt, BigIntSign, InvalidJsonNumber};
     pub use crate::data::store::{
-        AssignmentEvent, Attribute, Entity, NodeId, SubgraphEntityPair, SubgraphVersionSummary,
-        ToEntityId, ToEntityKey, TryIntoEntity, Value, ValueType,
+        AssignmentEvent, Attribute, Entity, EntitySet, EntityTypeError, NodeId, SubgraphEntityPair,
+        SubgraphVersionSummary, ToEntityId, ToEntityKey, TryIntoEntity, Value, ValueType,
     };
     pub use crate::data::subgraph::schema::{SubgraphDeploymentEntity, TypedEntity};
     pub use crate::data::subgraph::{
         BlockHandlerFilter, CreateSubgraphResult, DataSource, DataSourceContext,
-        DataSourceTemplate, Link, MappingABI, MappingBlockHandler, MappingCallHandler,
-        MappingEventHandler, SubgraphAssignmentProviderError, SubgraphAssignmentProviderEvent,
-        SubgraphDeploymentId, SubgraphManifest, SubgraphManifestResolveError,
-        SubgraphManifestValidationError, SubgraphName, SubgraphRegistrarError,
+        DataSourceTemplate, IndexerHints, Link, ManifestCache, MappingABI, MappingBlockHandler,
+        MappingCallHandler, MappingEventHandler, PruneHint, SubgraphAssignmentProviderError,
+        SubgraphAssignmentProviderEvent, SubgraphDeploymentId, SubgraphFeature, SubgraphManifest,
+        SubgraphManifestResolveError, SubgraphManifestValidationError,
+        SubgraphManifestValidationWarning, SubgraphName, SubgraphRegistrarError,
         UnvalidatedSubgraphManifest,
     };
     pub use crate::data::subscription::{
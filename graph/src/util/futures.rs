@@ -3,10 +3,11 @@ use failure::Fail;
 use futures::prelude::*;
 use slog::{debug, trace, warn, Logger};
 use std::fmt::Debug;
+use std::iter;
 use std::marker::PhantomData;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio_retry::strategy::{jitter, ExponentialBackoff};
+use tokio_retry::strategy::jitter;
 use tokio_retry::Retry;
 
 /// Generic helper function for retrying async operations with built-in logging.
@@ -15,7 +16,7 @@ use tokio_retry::Retry;
 ///
 /// 1. Call this function with an operation name (used for logging) and a `Logger`.
 /// 2. Optional: Chain a call to `.when(...)` to set a custom retry condition.
-/// 3. Optional: call `.log_after(...)` or `.no_logging()`.
+/// 3. Optional: call `.log_after(...)` or `.no_logging()`, and `.backoff(...)`.
 /// 4. Call either `.limit(...)` or `.no_limit()`.
 /// 5. Call one of `.timeout_secs(...)`, `.timeout_millis(...)`, `.timeout(...)`, and
 ///    `.no_timeout()`.
@@ -53,6 +54,7 @@ pub fn retry<I, E>(operation_name: impl ToString, logger: &Logger) -> RetryConfi
         log_after: 1,
         warn_after: 10,
         limit: RetryConfigProperty::Unknown,
+        backoff: Duration::from_millis(2),
         phantom_item: PhantomData,
         phantom_error: PhantomData,
     }
@@ -65,6 +67,7 @@ pub struct RetryConfig<I, E> {
     log_after: u64,
     warn_after: u64,
     limit: RetryConfigProperty<usize>,
+    backoff: Duration,
     phantom_item: PhantomData<I>,
     phantom_error: PhantomData<E>,
 }
@@ -117,6 +120,13 @@ where
         self
     }
 
+    /// Set how long to wait before the first retry. The wait doubles with
+    /// each further retry, up to a maximum of 30 seconds. Defaults to 2ms.
+    pub fn backoff(mut self, base: Duration) -> Self {
+        self.backoff = base;
+        self
+    }
+
     /// Set how long (in seconds) to wait for an attempt to complete before giving up on that
     /// attempt.
     pub fn timeout_secs(self, timeout_secs: u64) -> RetryConfigWithTimeout<I, E> {
@@ -167,6 +177,7 @@ where
         let log_after = self.inner.log_after;
         let warn_after = self.inner.warn_after;
         let limit_opt = self.inner.limit.unwrap(&operation_name, "limit");
+        let backoff = self.inner.backoff;
         let timeout = self.timeout;
 
         trace!(logger, "Run with retry: {}", operation_name);
@@ -178,6 +189,7 @@ where
             log_after,
             warn_after,
             limit_opt,
+            backoff,
             move || {
                 try_it()
                     .timeout(timeout)
@@ -208,6 +220,7 @@ impl<I, E> RetryConfigNoTimeout<I, E> {
         let log_after = self.inner.log_after;
         let warn_after = self.inner.warn_after;
         let limit_opt = self.inner.limit.unwrap(&operation_name, "limit");
+        let backoff = self.inner.backoff;
 
         trace!(logger, "Run with retry: {}", operation_name);
 
@@ -218,6 +231,7 @@ impl<I, E> RetryConfigNoTimeout<I, E> {
             log_after,
             warn_after,
             limit_opt,
+            backoff,
             // No timeout, so all errors are inner errors
             move || try_it().map_err(TimeoutError::Inner),
         )
@@ -259,6 +273,7 @@ fn run_retry<I, E, F, R>(
     log_after: u64,
     warn_after: u64,
     limit_opt: Option<usize>,
+    backoff: Duration,
     mut try_it_with_timeout: F,
 ) -> impl Future<Item = I, Error = TimeoutError<E>> + Send
 where
@@ -270,7 +285,7 @@ where
     let condition = Arc::new(condition);
 
     let mut attempt_count = 0;
-    Retry::spawn(retry_strategy(limit_opt), move || {
+    Retry::spawn(retry_strategy(limit_opt, backoff), move || {
         let operation_name = operation_name.clone();
         let logger = logger.clone();
         let condition = condition.clone();
@@ -345,12 +360,16 @@ where
     })
 }
 
-fn retry_strategy(limit_opt: Option<usize>) -> Box<dyn Iterator<Item = Duration> + Send> {
+fn retry_strategy(
+    limit_opt: Option<usize>,
+    base: Duration,
+) -> Box<dyn Iterator<Item = Duration> + Send> {
     // Exponential backoff, but with a maximum
-    let max_delay_ms = 30_000;
-    let backoff = ExponentialBackoff::from_millis(2)
-        .max_delay(Duration::from_millis(max_delay_ms))
-        .map(jitter);
+    let max_delay = Duration::from_millis(30_000);
+    let backoff = iter::successors(Some(base.min(max_delay)), move |delay| {
+        Some((*delay * 2).min(max_delay))
+    })
+    .map(jitter);
 
     // Apply limit (maximum retry count)
     match limit_opt {
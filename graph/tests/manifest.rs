@@ -1,20 +1,35 @@
 use async_trait::async_trait;
 use slog::Logger;
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
-use graph::components::link_resolver::{JsonValueStream, LinkResolver as LinkResolverTrait};
+use graph::components::link_resolver::{
+    with_link_timeout, ArweaveLinkResolver, CachingLinkResolver, ContentHashMismatch,
+    FileLinkResolver, FileSizeLimitExceeded, HttpClient, LinkRecording,
+    LinkResolver as LinkResolverTrait, LinkTimeout, RecordingLinkResolver, ReplayLinkResolver,
+    VerifyingLinkResolver,
+};
+use graph::components::store::ChainStore;
+use graph::data::subgraph::MAX_SPEC_VERSION;
 use graph::prelude::{
-    Entity, Link, SubgraphDeploymentId, SubgraphManifest, SubgraphManifestValidationError,
+    future, retry, Entity, Future01CompatExt, Link, ManifestCache, PruneHint, Schema,
+    SubgraphDeploymentId, SubgraphFeature, SubgraphManifest, SubgraphManifestResolveError,
+    SubgraphManifestValidationError, SubgraphManifestValidationWarning, TryStreamExt,
     UnvalidatedSubgraphManifest,
 };
 
-use test_store::LOGGER;
+use test_store::block_store::{self, BLOCK_ONE, BLOCK_TWO, GENESIS_BLOCK};
+use test_store::{LOGGER, NETWORK_NAME};
 
 #[derive(Default)]
 struct TextResolver {
     texts: HashMap<String, String>,
+    max_file_size: Option<usize>,
+    // The number of times `cat` was called
+    cats: Arc<AtomicUsize>,
 }
 
 impl TextResolver {
@@ -29,27 +44,158 @@ impl LinkResolverTrait for TextResolver {
         self
     }
 
-    fn with_retries(self) -> Self {
+    fn with_retries_config(self, _max_attempts: u32, _base_backoff: Duration) -> Self {
+        self
+    }
+
+    fn with_max_file_size(mut self, bytes: usize) -> Self {
+        self.max_file_size = Some(bytes);
         self
     }
 
     async fn cat(&self, _logger: &Logger, link: &Link) -> Result<Vec<u8>, failure::Error> {
-        self.texts
+        self.cats.fetch_add(1, Ordering::SeqCst);
+        let text = self
+            .texts
             .get(&link.link)
-            .ok_or(failure::format_err!("No text for {}", &link.link))
-            .map(|text| text.to_owned().into_bytes())
+            .ok_or(failure::format_err!("No text for {}", &link.link))?;
+        match self.max_file_size {
+            Some(max_file_size) if text.len() > max_file_size => Err(FileSizeLimitExceeded {
+                link: link.link.clone(),
+                max_file_size,
+                size: text.len() as u64,
+            }
+            .into()),
+            _ => Ok(text.to_owned().into_bytes()),
+        }
+    }
+}
+
+/// A resolver whose `cat` fails a number of times before it succeeds. It
+/// retries the way the IPFS resolver does, according to the configuration
+/// set with `with_retries_config`
+struct FlakyResolver {
+    failures: usize,
+    attempts: Arc<AtomicUsize>,
+    max_attempts: u32,
+    base_backoff: Duration,
+}
+
+impl FlakyResolver {
+    fn new(failures: usize) -> Self {
+        Self {
+            failures,
+            attempts: Arc::new(AtomicUsize::new(0)),
+            max_attempts: 1,
+            base_backoff: Duration::from_millis(1),
+        }
+    }
+}
+
+#[async_trait]
+impl LinkResolverTrait for FlakyResolver {
+    fn with_timeout(self, _timeout: Duration) -> Self {
+        self
+    }
+
+    fn with_retries_config(self, max_attempts: u32, base_backoff: Duration) -> Self {
+        Self {
+            max_attempts,
+            base_backoff,
+            ..self
+        }
+    }
+
+    fn with_max_file_size(self, _bytes: usize) -> Self {
+        self
+    }
+
+    async fn cat(&self, logger: &Logger, link: &Link) -> Result<Vec<u8>, failure::Error> {
+        let attempts = self.attempts.clone();
+        let failures = self.failures;
+        let text = link.link.clone();
+        retry("flaky.cat", logger)
+            .limit(self.max_attempts as usize)
+            .backoff(self.base_backoff)
+            .no_timeout()
+            .run(move || {
+                if attempts.fetch_add(1, Ordering::SeqCst) < failures {
+                    future::err(failure::format_err!("Flaky failure"))
+                } else {
+                    future::ok(text.clone().into_bytes())
+                }
+            })
+            .compat()
+            .await
+    }
+}
+
+/// A resolver that serves texts like `TextResolver`, but never finishes
+/// fetching the links in `slow`. Like the IPFS resolver, it gives up on a
+/// link after the timeout set with `with_timeout`
+struct SlowResolver {
+    texts: TextResolver,
+    slow: Vec<String>,
+    timeout: Duration,
+}
+
+#[async_trait]
+impl LinkResolverTrait for SlowResolver {
+    fn with_timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+
+    fn with_retries_config(self, _max_attempts: u32, _base_backoff: Duration) -> Self {
+        self
+    }
+
+    fn with_max_file_size(self, _bytes: usize) -> Self {
+        self
+    }
+
+    async fn cat(&self, logger: &Logger, link: &Link) -> Result<Vec<u8>, failure::Error> {
+        with_link_timeout(link, self.timeout, async {
+            if self.slow.contains(&link.link) {
+                tokio::time::delay_for(Duration::from_secs(60)).await;
+            }
+            self.texts.cat(logger, link).await
+        })
+        .await
     }
+}
+
+/// An `HttpClient` that serves fixed responses and remembers the URLs it
+/// was asked for
+#[derive(Default)]
+struct MockHttpClient {
+    responses: HashMap<String, String>,
+    requests: Arc<Mutex<Vec<String>>>,
+}
 
-    async fn json_stream(
-        &self,
-        _logger: &Logger,
-        _link: &Link,
-    ) -> Result<JsonValueStream, failure::Error> {
-        unimplemented!()
+#[async_trait]
+impl HttpClient for MockHttpClient {
+    async fn get(&self, url: &str, max_size: Option<usize>) -> Result<Vec<u8>, failure::Error> {
+        self.requests.lock().unwrap().push(url.to_owned());
+        let text = self
+            .responses
+            .get(url)
+            .ok_or_else(|| failure::format_err!("404 Not Found: {}", url))?;
+        match max_size {
+            Some(max_file_size) if text.len() > max_file_size => Err(FileSizeLimitExceeded {
+                link: url.to_owned(),
+                max_file_size,
+                size: text.len() as u64,
+            }
+            .into()),
+            _ => Ok(text.clone().into_bytes()),
+        }
     }
 }
 
 const GQL_SCHEMA: &str = "type Thing @entity { id: ID! }";
+const ABI: &str = "[]";
+// An empty WASM module, consisting of just the magic number and version
+const MAPPING: &str = "\0asm\u{1}\0\0\0";
 
 async fn resolve_manifest(text: &str) -> SubgraphManifest {
     let mut resolver = TextResolver::default();
@@ -64,17 +210,104 @@ async fn resolve_manifest(text: &str) -> SubgraphManifest {
 }
 
 async fn resolve_unvalidated(text: &str) -> UnvalidatedSubgraphManifest {
+    resolve_unvalidated_with_schema(text, GQL_SCHEMA).await
+}
+
+async fn resolve_unvalidated_with_schema(text: &str, schema: &str) -> UnvalidatedSubgraphManifest {
     let mut resolver = TextResolver::default();
     let link = Link::from("/ipfs/Qmmanifest".to_owned());
 
     resolver.add(link.link.as_str(), text);
-    resolver.add("/ipfs/Qmschema", GQL_SCHEMA);
+    resolver.add("/ipfs/Qmschema", schema);
+    resolver.add("/ipfs/Qmabi", ABI);
+    resolver.add("/ipfs/Qmmapping", MAPPING);
 
     UnvalidatedSubgraphManifest::resolve(link, Arc::new(resolver), &LOGGER)
         .await
         .expect("Parsing simple manifest works")
 }
 
+/// A manifest with one data source whose `source` uses the ABI `source_abi`
+/// and starts at `start_block`
+fn data_source_manifest(source_abi: &str, start_block: u64) -> String {
+    format!(
+        "
+dataSources:
+  - kind: ethereum/contract
+    name: Thing
+    network: {}
+    source:
+      abi: {}
+      startBlock: {}
+    mapping:
+      kind: ethereum/events
+      apiVersion: 0.0.3
+      language: wasm/assemblyscript
+      entities:
+        - Thing
+      abis:
+        - name: Thing
+          file:
+            /: /ipfs/Qmabi
+      file:
+        /: /ipfs/Qmmapping
+schema:
+  file:
+    /: /ipfs/Qmschema
+specVersion: 0.0.1
+",
+        NETWORK_NAME, source_abi, start_block
+    )
+}
+
+fn chain_stores<C: ChainStore>(store: Arc<C>) -> HashMap<String, Arc<C>> {
+    let mut chain_stores = HashMap::new();
+    chain_stores.insert(NETWORK_NAME.to_owned(), store);
+    chain_stores
+}
+
+/// Resolves the manifest `text` with `schema` as its schema and validates
+/// it against the test store
+fn validate_manifest(
+    text: &str,
+    schema: &str,
+) -> Result<
+    (SubgraphManifest, Vec<SubgraphManifestValidationWarning>),
+    Vec<SubgraphManifestValidationError>,
+> {
+    let store = test_store::STORE.clone();
+    test_store::STORE_RUNTIME.lock().unwrap().block_on(async {
+        resolve_unvalidated_with_schema(text, schema)
+            .await
+            .validate(store.clone(), &chain_stores(store))
+    })
+}
+
+/// Validates the manifest `text` with `schema` as its schema, and returns
+/// the validation errors for which `pred` holds, as strings; a valid
+/// manifest has none.
+fn validation_errors_with_schema(
+    text: &str,
+    schema: &str,
+    pred: impl Fn(&SubgraphManifestValidationError) -> bool,
+) -> Vec<String> {
+    validate_manifest(text, schema)
+        .err()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|e| pred(e))
+        .map(|e| e.to_string())
+        .collect()
+}
+
+/// Like `validation_errors_with_schema`, with `GQL_SCHEMA` as the schema
+fn validation_errors(
+    text: &str,
+    pred: impl Fn(&SubgraphManifestValidationError) -> bool,
+) -> Vec<String> {
+    validation_errors_with_schema(text, GQL_SCHEMA, pred)
+}
+
 #[tokio::test]
 async fn simple_manifest() {
     const YAML: &str = "
@@ -92,83 +325,1418 @@ specVersion: 0.0.1
 }
 
 #[tokio::test]
-async fn graft_manifest() {
+async fn file_link_resolver_manifest() {
     const YAML: &str = "
 dataSources: []
 schema:
   file:
-    /: /ipfs/Qmschema
-graft:
-  base: Qmbase
-  block: 12345
+    /: file:schema.graphql
 specVersion: 0.0.1
 ";
 
-    let manifest = resolve_manifest(YAML).await;
+    let dir = std::env::temp_dir().join(format!("file-link-resolver-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("manifest"), YAML).unwrap();
+    fs::write(dir.join("schema.graphql"), GQL_SCHEMA).unwrap();
 
-    assert_eq!("Qmmanifest", manifest.id.as_str());
-    let graft = manifest.graft.expect("The manifest has a graft base");
-    assert_eq!("Qmbase", graft.base.as_str());
-    assert_eq!(12345, graft.block);
+    let resolver = FileLinkResolver::new(&dir);
+    let link = Link::from("manifest".to_owned());
+    let manifest = SubgraphManifest::resolve(link, &resolver, &LOGGER).await;
+
+    // Links can not escape the base directory
+    let escape = resolver
+        .cat(&LOGGER, &Link::from("file:../schema.graphql".to_owned()))
+        .await;
+
+    // Not even through a symlink
+    let outside = dir.with_extension("outside");
+    fs::write(&outside, GQL_SCHEMA).unwrap();
+    std::os::unix::fs::symlink(&outside, dir.join("link.graphql")).unwrap();
+    let symlink_escape = resolver
+        .cat(&LOGGER, &Link::from("file:link.graphql".to_owned()))
+        .await;
+
+    fs::remove_dir_all(&dir).unwrap();
+    fs::remove_file(&outside).unwrap();
+
+    let manifest = manifest.expect("Resolving from files works");
+    assert_eq!("manifest", manifest.id.as_str());
+    assert_eq!(
+        Schema::parse(GQL_SCHEMA, manifest.id.clone())
+            .unwrap()
+            .document
+            .to_string(),
+        manifest.schema.document.to_string()
+    );
+    assert!(escape
+        .unwrap_err()
+        .to_string()
+        .starts_with("link `file:../schema.graphql` points outside of the directory"));
+    assert!(symlink_escape
+        .unwrap_err()
+        .to_string()
+        .starts_with("link `file:link.graphql` points outside of the directory"));
 }
 
-#[test]
-fn graft_invalid_manifest() {
+#[tokio::test]
+async fn unsupported_spec_version_manifest() {
     const YAML: &str = "
 dataSources: []
 schema:
   file:
     /: /ipfs/Qmschema
-graft:
-  base: Qmbase
-  block: 1
-specVersion: 0.0.1
+specVersion: 99.0.0
 ";
 
-    let store = test_store::STORE.clone();
+    let mut resolver = TextResolver::default();
+    let link = Link::from("/ipfs/Qmmanifest".to_owned());
 
-    test_store::STORE_RUNTIME.lock().unwrap().block_on(async {
-        let unvalidated = resolve_unvalidated(YAML).await;
-        let subgraph = SubgraphDeploymentId::new("Qmbase").unwrap();
+    resolver.add(link.link.as_str(), YAML);
+    resolver.add("/ipfs/Qmschema", GQL_SCHEMA);
 
-        //
-        // Validation against subgraph that hasn't synced anything fails
-        //
-        test_store::create_test_subgraph(subgraph.as_str(), GQL_SCHEMA);
-        // This check is awkward since the test manifest has other problems
-        // that the validation complains about as setting up a valid manifest
-        // would be a bit more work; we just want to make sure that
-        // graft-related checks work
-        let msg = unvalidated
-            .validate(store.clone())
-            .expect_err("Validation must fail")
-            .into_iter()
-            .find(|e| matches!(e, SubgraphManifestValidationError::GraftBaseInvalid(_)))
-            .expect("There must be a GraftBaseInvalid error")
-            .to_string();
-        assert_eq!(
-            "the graft base is invalid: failed to graft onto `Qmbase` since \
-            it has not processed any blocks",
-            msg
-        );
+    let e = SubgraphManifest::resolve(link, &resolver, &LOGGER)
+        .await
+        .expect_err("Resolving a manifest with an unsupported spec version fails");
+    match e {
+        SubgraphManifestResolveError::ResolveError(e) => {
+            let e = e.downcast_ref::<SubgraphManifestValidationError>();
+            assert!(matches!(
+                e,
+                Some(SubgraphManifestValidationError::UnsupportedSpecVersion(v, _)) if v == "99.0.0"
+            ));
+            assert_eq!(
+                format!(
+                    "manifest spec version `99.0.0` is not supported, this Graph Node only \
+                     supports manifest spec versions <= {}",
+                    *MAX_SPEC_VERSION
+                ),
+                e.unwrap().to_string()
+            );
+        }
+        e => panic!("unexpected error: {}", e),
+    }
+}
 
-        let mut thing = Entity::new();
-        thing.set("id", "datthing");
-        test_store::insert_entities(subgraph, vec![("Thing", thing)]).expect("Can insert a thing");
+#[tokio::test]
+async fn cat_many_preserves_order() {
+    let mut resolver = TextResolver::default();
+    let links: Vec<Link> = (0..20)
+        .map(|i| {
+            let link = format!("/ipfs/Qmtext{}", i);
+            resolver.add(&link, &format!("text {}", i));
+            Link::from(link)
+        })
+        .collect();
 
-        // Validation against subgraph that has not reached the graft point fails
-        let unvalidated = resolve_unvalidated(YAML).await;
-        let msg = unvalidated
-            .validate(store)
-            .expect_err("Validation must fail")
-            .into_iter()
-            .find(|e| matches!(e, SubgraphManifestValidationError::GraftBaseInvalid(_)))
-            .expect("There must be a GraftBaseInvalid error")
-            .to_string();
-        assert_eq!(
-            "the graft base is invalid: failed to graft onto `Qmbase` \
-            at block 1 since it has only processed block 0",
-            msg
-        );
+    let texts = resolver
+        .cat_many(&LOGGER, &links)
+        .await
+        .expect("All links can be resolved");
+
+    let expected: Vec<Vec<u8>> = (0..20)
+        .map(|i| format!("text {}", i).into_bytes())
+        .collect();
+    assert_eq!(expected, texts);
+}
+
+#[tokio::test]
+async fn cat_many_propagates_errors() {
+    let mut resolver = TextResolver::default();
+    resolver.add("/ipfs/Qmone", "one");
+    resolver.add("/ipfs/Qmthree", "three");
+
+    let links = vec![
+        Link::from("/ipfs/Qmone".to_owned()),
+        Link::from("/ipfs/Qmtwo".to_owned()),
+        Link::from("/ipfs/Qmthree".to_owned()),
+    ];
+
+    let e = resolver
+        .cat_many(&LOGGER, &links)
+        .await
+        .expect_err("Resolving a missing link fails");
+    assert_eq!("No text for /ipfs/Qmtwo", e.to_string());
+}
+
+#[tokio::test]
+async fn cat_enforces_max_file_size() {
+    let mut resolver = TextResolver::default();
+    resolver.add("/ipfs/Qmsmall", "small");
+    resolver.add("/ipfs/Qmlarge", "this text is too large");
+    let resolver = resolver.with_max_file_size(5);
+
+    let small = resolver
+        .cat(&LOGGER, &Link::from("/ipfs/Qmsmall".to_owned()))
+        .await
+        .expect("A file at the size limit can be resolved");
+    assert_eq!(b"small".to_vec(), small);
+
+    let e = resolver
+        .cat(&LOGGER, &Link::from("/ipfs/Qmlarge".to_owned()))
+        .await
+        .expect_err("A file above the size limit can not be resolved");
+    assert_eq!(
+        Some(&FileSizeLimitExceeded {
+            link: "/ipfs/Qmlarge".to_owned(),
+            max_file_size: 5,
+            size: 22,
+        }),
+        e.downcast_ref::<FileSizeLimitExceeded>()
+    );
+}
+
+#[tokio::test]
+async fn cat_retries_with_config() {
+    let link = Link::from("/ipfs/Qmflaky".to_owned());
+
+    // Succeeds on the third attempt
+    let resolver = FlakyResolver::new(2).with_retries_config(3, Duration::from_millis(1));
+    let data = resolver
+        .cat(&LOGGER, &link)
+        .await
+        .expect("Third attempt works");
+    assert_eq!(link.link.as_bytes(), data.as_slice());
+    assert_eq!(3, resolver.attempts.load(Ordering::SeqCst));
+
+    // Gives up after the second attempt
+    let resolver = FlakyResolver::new(2).with_retries_config(2, Duration::from_millis(1));
+    let err = resolver.cat(&LOGGER, &link).await.unwrap_err();
+    assert_eq!("Flaky failure", err.to_string());
+    assert_eq!(2, resolver.attempts.load(Ordering::SeqCst));
+}
+
+#[tokio::test]
+async fn with_link_timeout_times_out() {
+    let link = Link::from("/ipfs/Qmmanifest".to_owned());
+
+    let e = with_link_timeout(&link, Duration::from_millis(10), async {
+        tokio::time::delay_for(Duration::from_secs(60)).await;
+        Ok(())
+    })
+    .await
+    .unwrap_err();
+    assert_eq!(
+        Some(&LinkTimeout {
+            link: "/ipfs/Qmmanifest".to_owned(),
+            timeout: Duration::from_millis(10),
+        }),
+        e.downcast_ref::<LinkTimeout>()
+    );
+
+    // Errors from the future itself are passed through
+    let e = with_link_timeout(&link, Duration::from_secs(60), async {
+        Err::<(), _>(failure::format_err!("no such file"))
     })
+    .await
+    .unwrap_err();
+    assert_eq!("no such file", e.to_string());
+}
+
+#[tokio::test]
+async fn resolve_manifest_times_out() {
+    let mut texts = TextResolver::default();
+    let link = Link::from("/ipfs/Qmmanifest".to_owned());
+    texts.add(link.link.as_str(), &data_source_manifest("Thing", 0));
+    texts.add("/ipfs/Qmschema", GQL_SCHEMA);
+    texts.add("/ipfs/Qmmapping", MAPPING);
+    let resolver = SlowResolver {
+        texts,
+        slow: vec!["/ipfs/Qmabi".to_owned()],
+        timeout: Duration::from_secs(60),
+    }
+    .with_timeout(Duration::from_millis(10));
+
+    let e = SubgraphManifest::resolve(link, &resolver, &LOGGER)
+        .await
+        .expect_err("Resolving a manifest whose ABI can not be fetched fails");
+    match e {
+        SubgraphManifestResolveError::ResolveError(e) => assert_eq!(
+            Some(&LinkTimeout {
+                link: "/ipfs/Qmabi".to_owned(),
+                timeout: Duration::from_millis(10),
+            }),
+            e.downcast_ref::<LinkTimeout>()
+        ),
+        e => panic!("unexpected error: {}", e),
+    }
+}
+
+#[tokio::test]
+async fn arweave_resolver_cat() {
+    let mut client = MockHttpClient::default();
+    client.responses.insert(
+        "https://arweave.net/8uTVcVQBKdxmJ6pNmSfHkW9mfgTq0F2BJqDZZHMJjXY".to_owned(),
+        GQL_SCHEMA.to_owned(),
+    );
+    let requests = client.requests.clone();
+    let resolver = ArweaveLinkResolver::new(client, "https://arweave.net/");
+
+    let link = Link::from("ar://8uTVcVQBKdxmJ6pNmSfHkW9mfgTq0F2BJqDZZHMJjXY".to_owned());
+    let data = resolver
+        .cat(&LOGGER, &link)
+        .await
+        .expect("Can cat ar:// link");
+    assert_eq!(GQL_SCHEMA.as_bytes(), data.as_slice());
+    assert_eq!(
+        vec!["https://arweave.net/8uTVcVQBKdxmJ6pNmSfHkW9mfgTq0F2BJqDZZHMJjXY"],
+        *requests.lock().unwrap()
+    );
+
+    // Other links are rejected without a request
+    let err = resolver
+        .cat(&LOGGER, &Link::from("/ipfs/Qmschema".to_owned()))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        "link `/ipfs/Qmschema` is not an Arweave link",
+        err.to_string()
+    );
+    assert_eq!(1, requests.lock().unwrap().len());
+}
+
+#[tokio::test]
+async fn arweave_resolver_enforces_max_file_size() {
+    let mut client = MockHttpClient::default();
+    client.responses.insert(
+        "https://arweave.net/8uTVcVQBKdxmJ6pNmSfHkW9mfgTq0F2BJqDZZHMJjXY".to_owned(),
+        GQL_SCHEMA.to_owned(),
+    );
+    let resolver = ArweaveLinkResolver::new(client, "https://arweave.net")
+        .with_max_file_size(GQL_SCHEMA.len() - 1);
+
+    let link = Link::from("ar://8uTVcVQBKdxmJ6pNmSfHkW9mfgTq0F2BJqDZZHMJjXY".to_owned());
+    let err = resolver.cat(&LOGGER, &link).await.unwrap_err();
+    assert_eq!(
+        Some(&FileSizeLimitExceeded {
+            link: link.link.clone(),
+            max_file_size: GQL_SCHEMA.len() - 1,
+            size: GQL_SCHEMA.len() as u64,
+        }),
+        err.downcast_ref::<FileSizeLimitExceeded>()
+    );
+}
+
+#[tokio::test]
+async fn manifest_cache_resolves_once() {
+    const YAML: &str = "
+dataSources: []
+schema:
+  file:
+    /: /ipfs/Qmschema
+specVersion: 0.0.1
+";
+
+    let mut resolver = TextResolver::default();
+    resolver.add("/ipfs/Qmmanifest", YAML);
+    resolver.add("/ipfs/Qmschema", GQL_SCHEMA);
+    let cats = resolver.cats.clone();
+    let cache = ManifestCache::new(Duration::from_secs(60));
+
+    let link = Link::from("/ipfs/Qmmanifest".to_owned());
+    let first = cache
+        .resolve(link.clone(), &resolver, &LOGGER)
+        .await
+        .expect("Resolving the manifest works");
+    // Fetching the manifest and the schema
+    assert_eq!(2, cats.load(Ordering::SeqCst));
+
+    let second = cache
+        .resolve(link.clone(), &resolver, &LOGGER)
+        .await
+        .expect("Resolving the cached manifest works");
+    assert_eq!(2, cats.load(Ordering::SeqCst));
+    assert_eq!(first.content_hash(), second.content_hash());
+
+    // The link without the `/ipfs/` prefix names the same manifest
+    cache
+        .resolve(Link::from("Qmmanifest".to_owned()), &resolver, &LOGGER)
+        .await
+        .expect("Resolving the cached manifest works");
+    assert_eq!(2, cats.load(Ordering::SeqCst));
+
+    // Expired entries are resolved again
+    let cache = ManifestCache::new(Duration::from_secs(0));
+    cache
+        .resolve(link.clone(), &resolver, &LOGGER)
+        .await
+        .expect("Resolving the manifest works");
+    cache
+        .resolve(link, &resolver, &LOGGER)
+        .await
+        .expect("Resolving the manifest works");
+    assert_eq!(6, cats.load(Ordering::SeqCst));
+}
+
+#[tokio::test]
+async fn recorded_links_replay() {
+    const JSON: &str = "{\"a\": 1}\n\n[2, 3]\n";
+
+    let mut resolver = TextResolver::default();
+    resolver.add("/ipfs/Qmabi", ABI);
+    resolver.add("/ipfs/Qmjson", JSON);
+    let resolver = RecordingLinkResolver::new(resolver);
+
+    let abi_link = Link::from("/ipfs/Qmabi".to_owned());
+    let json_link = Link::from("/ipfs/Qmjson".to_owned());
+    let abi = resolver.cat(&LOGGER, &abi_link).await.unwrap();
+    let values: Vec<_> = resolver
+        .json_stream(&LOGGER, &json_link)
+        .await
+        .unwrap()
+        .map_ok(|value| (value.line, value.byte_offset, value.value))
+        .try_collect()
+        .await
+        .unwrap();
+
+    let path = std::env::temp_dir().join(format!("link-recording-{}.json", std::process::id()));
+    resolver
+        .recording()
+        .save(&path)
+        .expect("Can save the recording");
+    let loaded = LinkRecording::load(&path);
+    let replay = ReplayLinkResolver::load(&path);
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        resolver.recording(),
+        loaded.expect("Can load the recording")
+    );
+    let replay = replay.expect("Can load the recording");
+    assert_eq!(abi, replay.cat(&LOGGER, &abi_link).await.unwrap());
+    let replayed: Vec<_> = replay
+        .json_stream(&LOGGER, &json_link)
+        .await
+        .unwrap()
+        .map_ok(|value| (value.line, value.byte_offset, value.value))
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(values, replayed);
+    assert_eq!(
+        vec![(1, 0), (3, 10)],
+        replayed
+            .iter()
+            .map(|(line, byte_offset, _)| (*line, *byte_offset))
+            .collect::<Vec<_>>()
+    );
+
+    // Links that were not recorded are an error
+    let missing = replay
+        .cat(&LOGGER, &Link::from("/ipfs/Qmmissing".to_owned()))
+        .await;
+    assert!(missing.is_err());
+}
+
+#[tokio::test]
+async fn caching_resolver_cats_once() {
+    let mut resolver = TextResolver::default();
+    resolver.add("/ipfs/Qmabi", ABI);
+    let cats = resolver.cats.clone();
+    let resolver = CachingLinkResolver::new(resolver, 10);
+
+    let links = vec![
+        Link::from("/ipfs/Qmabi".to_owned()),
+        Link::from("/ipfs/Qmabi".to_owned()),
+    ];
+    for link in &links {
+        let abi = resolver.cat(&LOGGER, link).await.expect("Can cat the ABI");
+        assert_eq!(ABI.as_bytes(), abi.as_slice());
+    }
+    assert_eq!(1, cats.load(Ordering::SeqCst));
+
+    // Cached files are still subject to the file size limit
+    let resolver = resolver.with_max_file_size(ABI.len() - 1);
+    let e = resolver
+        .cat(&LOGGER, &links[0])
+        .await
+        .expect_err("The cached ABI is too large");
+    assert_eq!(
+        Some(&FileSizeLimitExceeded {
+            link: "/ipfs/Qmabi".to_owned(),
+            max_file_size: ABI.len() - 1,
+            size: ABI.len() as u64,
+        }),
+        e.downcast_ref::<FileSizeLimitExceeded>()
+    );
+    assert_eq!(1, cats.load(Ordering::SeqCst));
+}
+
+#[tokio::test]
+async fn verifying_resolver_checks_hashes() {
+    // The hash `ipfs add` reports for a file containing "hello world\n"
+    const HELLO: &str = "/ipfs/QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o";
+    // The hash of an empty file
+    const EMPTY: &str = "/ipfs/QmbFMke1KXqnYyBBWxB74N4c5SBnJMVAiMNRcGu6x1AwQH";
+
+    let mut resolver = TextResolver::default();
+    resolver.add(HELLO, "hello world\n");
+    resolver.add(EMPTY, "");
+    resolver.add("/ipfs/Qmabi", ABI);
+    let resolver = VerifyingLinkResolver::new(resolver);
+
+    let data = resolver
+        .cat(&LOGGER, &Link::from(HELLO.to_owned()))
+        .await
+        .expect("Data with the right hash is returned");
+    assert_eq!(b"hello world\n", data.as_slice());
+    let data = resolver
+        .cat(&LOGGER, &Link::from(EMPTY.to_owned()))
+        .await
+        .expect("An empty file with the right hash is returned");
+    assert!(data.is_empty());
+
+    // Links that are not CIDs can not be verified and are passed through
+    let data = resolver
+        .cat(&LOGGER, &Link::from("/ipfs/Qmabi".to_owned()))
+        .await
+        .expect("Data for a link that is not a CID is returned");
+    assert_eq!(ABI.as_bytes(), data.as_slice());
+
+    // Data that does not match the hash is rejected
+    let mut resolver = TextResolver::default();
+    resolver.add(HELLO, "hello mallory\n");
+    let resolver = VerifyingLinkResolver::new(resolver);
+    let e = resolver
+        .cat(&LOGGER, &Link::from(HELLO.to_owned()))
+        .await
+        .expect_err("Data with the wrong hash is rejected");
+    let e = e
+        .downcast_ref::<ContentHashMismatch>()
+        .expect("The error is a ContentHashMismatch");
+    assert_eq!(HELLO, e.link);
+    assert_ne!(&HELLO["/ipfs/".len()..], e.actual);
+}
+
+#[tokio::test]
+async fn json_stream() {
+    let mut resolver = TextResolver::default();
+    resolver.add("/ipfs/Qmjson", "{\"id\": 1}\n\n\"two\"\n[3, 4]\n");
+
+    let values = resolver
+        .json_stream(&LOGGER, &Link::from("/ipfs/Qmjson".to_owned()))
+        .await
+        .expect("Can stream the JSON text")
+        .map_ok(|sv| (sv.line, sv.byte_offset, sv.value))
+        .try_collect::<Vec<_>>()
+        .await
+        .expect("All lines are valid JSON");
+
+    assert_eq!(
+        vec![
+            (1, 0, serde_json::json!({ "id": 1 })),
+            (3, 11, serde_json::json!("two")),
+            (4, 17, serde_json::json!([3, 4])),
+        ],
+        values
+    );
+}
+
+#[tokio::test]
+async fn list_manifest_links() {
+    const YAML: &str = "
+dataSources:
+  - kind: ethereum/contract
+    name: Thing
+    network: mainnet
+    source:
+      abi: Thing
+    mapping:
+      kind: ethereum/events
+      apiVersion: 0.0.3
+      language: wasm/assemblyscript
+      entities:
+        - Thing
+      abis:
+        - name: Thing
+          file:
+            /: /ipfs/Qmabi
+        - name: Token
+          file:
+            /: /ipfs/Qmtokenabi
+      file:
+        /: /ipfs/Qmmapping
+templates:
+  - kind: ethereum/contract
+    name: Factory
+    network: mainnet
+    source:
+      abi: Token
+    mapping:
+      kind: ethereum/events
+      apiVersion: 0.0.3
+      language: wasm/assemblyscript
+      entities:
+        - Thing
+      abis:
+        - name: Token
+          file:
+            /: /ipfs/Qmtokenabi
+      file:
+        /: /ipfs/Qmtemplatemapping
+schema:
+  file:
+    /: /ipfs/Qmschema
+specVersion: 0.0.1
+";
+
+    let mut resolver = TextResolver::default();
+    let link = Link::from("/ipfs/Qmmanifest".to_owned());
+    resolver.add(link.link.as_str(), YAML);
+    let cats = resolver.cats.clone();
+
+    let links = SubgraphManifest::list_links(link, &resolver, &LOGGER)
+        .await
+        .expect("Listing links works");
+    let links: Vec<_> = links.into_iter().map(|link| link.link).collect();
+    assert_eq!(
+        vec![
+            "/ipfs/Qmschema",
+            "/ipfs/Qmabi",
+            "/ipfs/Qmtokenabi",
+            "/ipfs/Qmmapping",
+            "/ipfs/Qmtemplatemapping"
+        ],
+        links
+    );
+    // Only the manifest itself was fetched
+    assert_eq!(1, cats.load(Ordering::SeqCst));
+}
+
+#[tokio::test]
+async fn manifest_content_hash() {
+    const YAML: &str = "
+dataSources: []
+schema:
+  file:
+    /: /ipfs/Qmschema
+specVersion: 0.0.1
+";
+    const OTHER_YAML: &str = "
+dataSources: []
+description: A different subgraph
+schema:
+  file:
+    /: /ipfs/Qmschema
+specVersion: 0.0.1
+";
+
+    let hash = resolve_manifest(YAML).await.content_hash();
+    assert_eq!(hash, resolve_manifest(YAML).await.content_hash());
+    assert_ne!(hash, resolve_manifest(OTHER_YAML).await.content_hash());
+}
+
+#[tokio::test]
+async fn missing_schema_manifest() {
+    const YAML: &str = "
+dataSources: []
+schema:
+  file:
+    /: /ipfs/Qmschema
+specVersion: 0.0.1
+";
+
+    let mut resolver = TextResolver::default();
+    let link = Link::from("/ipfs/Qmmanifest".to_owned());
+
+    resolver.add(link.link.as_str(), YAML);
+
+    let e = SubgraphManifest::resolve(link, &resolver, &LOGGER)
+        .await
+        .expect_err("Resolving a manifest without its schema fails");
+    match e {
+        SubgraphManifestResolveError::ResolveError(e) => assert_eq!(
+            "schema file `/ipfs/Qmschema` could not be resolved: No text for /ipfs/Qmschema",
+            e.downcast_ref::<SubgraphManifestValidationError>()
+                .expect("The error is a validation error")
+                .to_string()
+        ),
+        e => panic!("unexpected error: {}", e),
+    }
+}
+
+#[tokio::test]
+async fn strict_manifest_rejects_unknown_fields() {
+    // `datasources` is a misspelling of `dataSources`
+    const YAML: &str = "
+dataSources: []
+datasources:
+  - kind: ethereum/contract
+    name: Thing
+schema:
+  file:
+    /: /ipfs/Qmschema
+specVersion: 0.0.1
+";
+
+    let mut resolver = TextResolver::default();
+    let link = Link::from("/ipfs/Qmmanifest".to_owned());
+
+    resolver.add(link.link.as_str(), YAML);
+    resolver.add("/ipfs/Qmschema", GQL_SCHEMA);
+
+    let e = SubgraphManifest::resolve_strict(link.clone(), &resolver, &LOGGER)
+        .await
+        .expect_err("Strictly resolving a manifest with an unknown field fails");
+    match e {
+        SubgraphManifestResolveError::UnknownField(field) => assert_eq!("datasources", field),
+        e => panic!("unexpected error: {}", e),
+    }
+
+    // The lenient resolution ignores the unknown field
+    let manifest = SubgraphManifest::resolve(link, &resolver, &LOGGER)
+        .await
+        .expect("Leniently resolving a manifest with an unknown field works");
+    assert!(manifest.data_sources.is_empty());
+}
+
+#[tokio::test]
+async fn invalid_abi_manifest() {
+    let mut resolver = TextResolver::default();
+    let link = Link::from("/ipfs/Qmmanifest".to_owned());
+
+    resolver.add(link.link.as_str(), &data_source_manifest("Thing", 0));
+    resolver.add("/ipfs/Qmschema", GQL_SCHEMA);
+    resolver.add("/ipfs/Qmabi", "[{ not json");
+    resolver.add("/ipfs/Qmmapping", MAPPING);
+
+    let e = SubgraphManifest::resolve(link, &resolver, &LOGGER)
+        .await
+        .expect_err("Resolving a manifest with a broken ABI fails");
+    match e {
+        SubgraphManifestResolveError::ResolveError(e) => {
+            assert!(matches!(
+                e.downcast_ref::<SubgraphManifestValidationError>(),
+                Some(SubgraphManifestValidationError::AbiInvalid { name, .. }) if name == "Thing"
+            ));
+            assert!(e.to_string().starts_with("ABI `Thing` is invalid: "));
+        }
+        e => panic!("unexpected error: {}", e),
+    }
+}
+
+#[tokio::test]
+async fn graft_manifest() {
+    const YAML: &str = "
+dataSources: []
+schema:
+  file:
+    /: /ipfs/Qmschema
+graft:
+  base: Qmbase
+  block: 12345
+specVersion: 0.0.1
+";
+
+    let manifest = resolve_manifest(YAML).await;
+
+    assert_eq!("Qmmanifest", manifest.id.as_str());
+    let graft = manifest.graft.expect("The manifest has a graft base");
+    assert_eq!("Qmbase", graft.base.as_str());
+    assert_eq!(12345, graft.block);
+}
+
+#[tokio::test]
+async fn graft_chain_parses() {
+    const YAML: &str = "
+dataSources: []
+schema:
+  file:
+    /: /ipfs/Qmschema
+graft:
+  - base: Qmfirst
+    block: 10
+  - base: Qmsecond
+    block: 20
+specVersion: 0.0.1
+";
+
+    let manifest = resolve_manifest(YAML).await;
+
+    let graft = manifest.graft.expect("The manifest has a graft base");
+    let chain: Vec<_> = graft
+        .chain()
+        .map(|graft| (graft.base.as_str(), graft.block))
+        .collect();
+    assert_eq!(vec![("Qmfirst", 10), ("Qmsecond", 20)], chain);
+}
+
+#[test]
+fn graft_invalid_manifest() {
+    const YAML: &str = "
+dataSources: []
+schema:
+  file:
+    /: /ipfs/Qmschema
+graft:
+  base: Qmbase
+  block: 1
+specVersion: 0.0.1
+";
+
+    let store = test_store::STORE.clone();
+
+    test_store::STORE_RUNTIME.lock().unwrap().block_on(async {
+        let unvalidated = resolve_unvalidated(YAML).await;
+        let subgraph = SubgraphDeploymentId::new("Qmbase").unwrap();
+
+        //
+        // Validation against subgraph that hasn't synced anything fails
+        //
+        test_store::create_test_subgraph(subgraph.as_str(), GQL_SCHEMA);
+        // This check is awkward since the test manifest has other problems
+        // that the validation complains about as setting up a valid manifest
+        // would be a bit more work; we just want to make sure that
+        // graft-related checks work
+        let msg = unvalidated
+            .validate(store.clone(), &chain_stores(store.clone()))
+            .expect_err("Validation must fail")
+            .into_iter()
+            .find(|e| matches!(e, SubgraphManifestValidationError::GraftBaseInvalid(_)))
+            .expect("There must be a GraftBaseInvalid error")
+            .to_string();
+        assert_eq!(
+            "the graft base is invalid: failed to graft onto `Qmbase` at block 1 \
+            since it has not processed any blocks yet",
+            msg
+        );
+
+        let mut thing = Entity::new();
+        thing.set("id", "datthing");
+        test_store::insert_entities(subgraph, vec![("Thing", thing)]).expect("Can insert a thing");
+
+        // Validation against subgraph that has not reached the graft point fails
+        let unvalidated = resolve_unvalidated(YAML).await;
+        let msg = unvalidated
+            .validate(store.clone(), &chain_stores(store))
+            .expect_err("Validation must fail")
+            .into_iter()
+            .find(|e| matches!(e, SubgraphManifestValidationError::GraftBaseInvalid(_)))
+            .expect("There must be a GraftBaseInvalid error")
+            .to_string();
+        assert_eq!(
+            "the graft base is invalid: failed to graft onto `Qmbase` \
+            at block 1 since it has only processed block 0 so far",
+            msg
+        );
+    })
+}
+
+#[test]
+fn graft_invalid_base_id_manifest() {
+    const YAML: &str = "
+dataSources: []
+schema:
+  file:
+    /: /ipfs/Qmschema
+graft:
+  base: not!!valid
+  block: 1
+specVersion: 0.0.1
+";
+
+    let store = test_store::STORE.clone();
+
+    test_store::STORE_RUNTIME.lock().unwrap().block_on(async {
+        let unvalidated = resolve_unvalidated(YAML).await;
+        let msg = unvalidated
+            .validate(store.clone(), &chain_stores(store))
+            .expect_err("Validation must fail")
+            .into_iter()
+            .find(|e| matches!(e, SubgraphManifestValidationError::GraftBaseInvalid(_)))
+            .expect("There must be a GraftBaseInvalid error")
+            .to_string();
+        assert_eq!(
+            "the graft base is invalid: graft base `not!!valid` is not a valid deployment id",
+            msg
+        );
+    })
+}
+
+#[test]
+fn graft_far_beyond_base_manifest() {
+    const YAML: &str = "
+dataSources: []
+schema:
+  file:
+    /: /ipfs/Qmschema
+graft:
+  base: Qmfarbase
+  block: 5000000
+specVersion: 0.0.1
+";
+
+    let store = test_store::STORE.clone();
+
+    test_store::STORE_RUNTIME.lock().unwrap().block_on(async {
+        let subgraph = SubgraphDeploymentId::new("Qmfarbase").unwrap();
+        test_store::create_test_subgraph(subgraph.as_str(), GQL_SCHEMA);
+        let mut thing = Entity::new();
+        thing.set("id", "datthing");
+        test_store::insert_entities(subgraph, vec![("Thing", thing)]).expect("Can insert a thing");
+
+        let unvalidated = resolve_unvalidated(YAML).await;
+        let msg = unvalidated
+            .validate(store.clone(), &chain_stores(store))
+            .expect_err("Validation must fail")
+            .into_iter()
+            .find(|e| matches!(e, SubgraphManifestValidationError::GraftBaseInvalid(_)))
+            .expect("There must be a GraftBaseInvalid error")
+            .to_string();
+        assert_eq!(
+            "the graft base is invalid: failed to graft onto `Qmfarbase` at block 5000000 \
+             since that is more than 1000000 blocks beyond block 0, the latest block it has \
+             processed; the graft block is most likely wrong",
+            msg
+        );
+    })
+}
+
+#[test]
+fn graft_block_zero_manifest() {
+    const YAML: &str = "
+dataSources: []
+schema:
+  file:
+    /: /ipfs/Qmschema
+graft:
+  base: Qmbase
+  block: 0
+specVersion: 0.0.1
+";
+
+    let store = test_store::STORE.clone();
+
+    test_store::STORE_RUNTIME.lock().unwrap().block_on(async {
+        let unvalidated = resolve_unvalidated(YAML).await;
+        let msg = unvalidated
+            .validate(store.clone(), &chain_stores(store))
+            .expect_err("Validation must fail")
+            .into_iter()
+            .find(|e| matches!(e, SubgraphManifestValidationError::GraftBaseInvalid(_)))
+            .expect("There must be a GraftBaseInvalid error")
+            .to_string();
+        assert_eq!(
+            "the graft base is invalid: graft block must be greater than 0",
+            msg
+        );
+    })
+}
+
+#[test]
+fn graft_chain_manifest() {
+    const YAML: &str = "
+dataSources: []
+schema:
+  file:
+    /: /ipfs/Qmschema
+graft:
+  - base: QmchainA
+    block: 1
+  - base: QmchainB
+    block: 5
+specVersion: 0.0.1
+";
+
+    let store = test_store::STORE.clone();
+
+    test_store::STORE_RUNTIME.lock().unwrap().block_on(async {
+        // Both bases have processed block 1
+        for id in &["QmchainA", "QmchainB"] {
+            let subgraph = SubgraphDeploymentId::new(*id).unwrap();
+            test_store::create_test_subgraph(subgraph.as_str(), GQL_SCHEMA);
+
+            let mut thing = Entity::new();
+            thing.set("id", "datthing");
+            test_store::insert_entities(subgraph.clone(), vec![("Thing", thing)])
+                .expect("Can insert a thing");
+            test_store::transact_entity_operations(
+                &store,
+                subgraph,
+                test_store::BLOCK_ONE.clone(),
+                vec![],
+            )
+            .expect("Can advance to block 1");
+        }
+
+        let unvalidated = resolve_unvalidated(YAML).await;
+        let graft = unvalidated
+            .validate(store.clone(), &chain_stores(store))
+            .expect_err("Validation must fail")
+            .into_iter()
+            .filter(|e| matches!(e, SubgraphManifestValidationError::GraftBaseInvalid(_)))
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                "the graft base is invalid: failed to graft onto `QmchainB` \
+                 at block 5 since it has only processed block 1 so far"
+            ],
+            graft
+        );
+    })
+}
+
+#[test]
+fn graft_cycle_manifest() {
+    const YAML: &str = "
+dataSources: []
+schema:
+  file:
+    /: /ipfs/Qmschema
+graft:
+  base: QmcycleBase
+  block: 1
+specVersion: 0.0.1
+";
+
+    // Grafting is only supported with relational storage
+    if !*test_store::USING_RELATIONAL_STORAGE {
+        return;
+    }
+
+    let store = test_store::STORE.clone();
+
+    test_store::STORE_RUNTIME.lock().unwrap().block_on(async {
+        // The manifest resolves to `Qmmanifest`; set up `QmcycleBase` so
+        // that it is grafted onto `Qmmanifest`
+        let subgraph = SubgraphDeploymentId::new("Qmmanifest").unwrap();
+        test_store::create_test_subgraph(subgraph.as_str(), GQL_SCHEMA);
+        let mut thing = Entity::new();
+        thing.set("id", "datthing");
+        test_store::insert_entities(subgraph.clone(), vec![("Thing", thing)])
+            .expect("Can insert a thing");
+        test_store::transact_entity_operations(
+            &store,
+            subgraph.clone(),
+            test_store::BLOCK_ONE.clone(),
+            vec![],
+        )
+        .expect("Can advance to block 1");
+        test_store::create_grafted_subgraph(
+            "QmcycleBase",
+            GQL_SCHEMA,
+            subgraph.as_str(),
+            test_store::BLOCK_ONE.clone(),
+        )
+        .expect("Can graft onto Qmmanifest");
+
+        let unvalidated = resolve_unvalidated(YAML).await;
+        let msg = unvalidated
+            .validate(store.clone(), &chain_stores(store))
+            .expect_err("Validation must fail")
+            .into_iter()
+            .find(|e| matches!(e, SubgraphManifestValidationError::GraftCycle(_)))
+            .expect("There must be a GraftCycle error")
+            .to_string();
+        assert_eq!(
+            "the grafts form a cycle: Qmmanifest -> QmcycleBase -> Qmmanifest",
+            msg
+        );
+    })
+}
+
+#[test]
+fn start_block_manifest() {
+    fn start_block_warnings(text: &str) -> Vec<String> {
+        let (_, warnings) = validate_manifest(text, GQL_SCHEMA)
+            .expect("Start blocks never make a manifest invalid");
+        warnings
+            .into_iter()
+            .filter(|w| matches!(w, SubgraphManifestValidationWarning::StartBlockInFuture(..)))
+            .map(|w| w.to_string())
+            .collect()
+    }
+
+    block_store::remove();
+    block_store::insert(
+        vec![&*GENESIS_BLOCK, &*BLOCK_ONE, &*BLOCK_TWO],
+        NETWORK_NAME,
+    );
+    test_store::STORE
+        .attempt_chain_head_update(1)
+        .expect("Can update the chain head");
+
+    // A start block beyond the chain head validates, but with a warning
+    assert_eq!(
+        vec!["data source `Thing` starts at block 3, but the chain head is only at block 2"],
+        start_block_warnings(&data_source_manifest("Thing", 3))
+    );
+
+    // A start block at the chain head is fine
+    assert!(start_block_warnings(&data_source_manifest("Thing", 2)).is_empty());
+}
+
+#[test]
+fn source_abi_manifest() {
+    let abi_errors = |text: &str| {
+        validation_errors(text, |e| {
+            matches!(
+                e,
+                SubgraphManifestValidationError::AbiInvalid { .. }
+                    | SubgraphManifestValidationError::AbiNameNotDeclared { .. }
+            )
+        })
+    };
+
+    // The source uses an ABI the mapping doesn't list
+    assert_eq!(
+        vec![
+            "data source `Thing` uses ABI `Other`, but does not list it \
+             among the ABIs of its mapping"
+        ],
+        abi_errors(&data_source_manifest("Other", 0))
+    );
+
+    // The source uses an ABI from the mapping
+    assert!(abi_errors(&data_source_manifest("Thing", 0)).is_empty());
+}
+
+#[test]
+fn template_abi_manifest() {
+    fn template_manifest(source_abi: &str) -> String {
+        format!(
+            "{}templates:
+  - kind: ethereum/contract
+    name: Dynamic
+    network: {}
+    source:
+      abi: {}
+    mapping:
+      kind: ethereum/events
+      apiVersion: 0.0.3
+      language: wasm/assemblyscript
+      entities:
+        - Thing
+      abis:
+        - name: Thing
+          file:
+            /: /ipfs/Qmabi
+      file:
+        /: /ipfs/Qmmapping
+",
+            data_source_manifest("Thing", 0),
+            NETWORK_NAME,
+            source_abi
+        )
+    }
+
+    // The template uses an ABI its mapping doesn't list
+    assert_eq!(
+        vec![
+            "data source `Dynamic` uses ABI `Missing`, but does not list it \
+             among the ABIs of its mapping"
+        ],
+        validation_errors(&template_manifest("Missing"), |e| matches!(
+            e,
+            SubgraphManifestValidationError::AbiNameNotDeclared { .. }
+        ))
+    );
+
+    // The template uses an ABI from its mapping
+    assert!(validation_errors(&template_manifest("Thing"), |_| true).is_empty());
+}
+
+#[test]
+fn missing_description_manifest() {
+    fn has_missing_description(warnings: &[SubgraphManifestValidationWarning]) -> bool {
+        warnings
+            .iter()
+            .any(|w| matches!(w, SubgraphManifestValidationWarning::MissingDescription))
+    }
+
+    let store = test_store::STORE.clone();
+
+    test_store::STORE_RUNTIME.lock().unwrap().block_on(async {
+        // A manifest without a description validates, but with a warning
+        let unvalidated = resolve_unvalidated(&data_source_manifest("Thing", 0)).await;
+        let (manifest, warnings) = unvalidated
+            .validate(store.clone(), &chain_stores(store.clone()))
+            .expect("Manifest without a description is valid");
+        assert_eq!(None, manifest.description);
+        assert!(has_missing_description(&warnings));
+
+        // With a description, there is no warning
+        let text = format!("description: A thing{}", data_source_manifest("Thing", 0));
+        let unvalidated = resolve_unvalidated(&text).await;
+        let (_, warnings) = unvalidated
+            .validate(store.clone(), &chain_stores(store))
+            .expect("Manifest with a description is valid");
+        assert!(!has_missing_description(&warnings));
+    })
+}
+
+#[test]
+fn empty_manifest() {
+    const YAML: &str = "
+dataSources: []
+schema:
+  file:
+    /: /ipfs/Qmschema
+specVersion: 0.0.1
+";
+
+    let store = test_store::STORE.clone();
+
+    test_store::STORE_RUNTIME.lock().unwrap().block_on(async {
+        let unvalidated = resolve_unvalidated(YAML).await;
+        let errors = unvalidated
+            .validate(store.clone(), &chain_stores(store))
+            .expect_err("Validation of an empty manifest must fail");
+        assert!(errors
+            .iter()
+            .any(|e| matches!(e, SubgraphManifestValidationError::NoDataSourcesOrTemplates)));
+    })
+}
+
+#[test]
+fn template_only_manifest() {
+    let yaml = format!(
+        "
+dataSources: []
+templates:
+  - kind: ethereum/contract
+    name: Dynamic
+    network: {}
+    source:
+      abi: Thing
+    mapping:
+      kind: ethereum/events
+      apiVersion: 0.0.3
+      language: wasm/assemblyscript
+      entities:
+        - Thing
+      abis:
+        - name: Thing
+          file:
+            /: /ipfs/Qmabi
+      file:
+        /: /ipfs/Qmmapping
+schema:
+  file:
+    /: /ipfs/Qmschema
+specVersion: 0.0.1
+",
+        NETWORK_NAME
+    );
+
+    let store = test_store::STORE.clone();
+
+    test_store::STORE_RUNTIME.lock().unwrap().block_on(async {
+        let unvalidated = resolve_unvalidated(&yaml).await;
+        unvalidated
+            .validate(store.clone(), &chain_stores(store))
+            .expect("Manifest with only templates is valid");
+    })
+}
+
+#[test]
+fn api_version_manifest() {
+    // A supported API version passes
+    assert!(validation_errors(&data_source_manifest("Thing", 0), |_| true).is_empty());
+
+    // An API version that is newer than what we support fails
+    let text = data_source_manifest("Thing", 0).replace("apiVersion: 0.0.3", "apiVersion: 0.1.0");
+    assert_eq!(
+        vec![
+            "the mapping of data source `Thing` uses API version `0.1.0`, \
+              which is not supported"
+                .to_owned()
+        ],
+        validation_errors(&text, |e| matches!(
+            e,
+            SubgraphManifestValidationError::UnsupportedApiVersion { .. }
+        ))
+    );
+}
+
+#[test]
+fn network_manifest() {
+    let network_errors = |text: &str| {
+        validation_errors(text, |e| {
+            matches!(e, SubgraphManifestValidationError::NetworkNotSupported(_))
+        })
+    };
+
+    // A network this node does not know about is rejected
+    let text = data_source_manifest("Thing", 0)
+        .replace(&format!("network: {}", NETWORK_NAME), "network: maimnet");
+    assert_eq!(
+        vec!["network `maimnet` is not supported by this Graph Node"],
+        network_errors(&text)
+    );
+
+    // A known network is fine
+    assert!(network_errors(&data_source_manifest("Thing", 0)).is_empty());
+}
+
+#[test]
+fn schema_without_entities_manifest() {
+    let no_entities_errors = |schema: &str| {
+        validation_errors_with_schema(&data_source_manifest("Thing", 0), schema, |e| {
+            matches!(e, SubgraphManifestValidationError::SchemaHasNoEntities)
+        })
+    };
+
+    // A schema without any entity types is rejected
+    assert_eq!(
+        vec!["the schema does not define any types with an @entity directive"],
+        no_entities_errors("enum Kind { Small, Large }")
+    );
+
+    // GQL_SCHEMA has an entity type
+    assert!(no_entities_errors(GQL_SCHEMA).is_empty());
+}
+
+#[test]
+fn indexer_hints_manifest() {
+    fn hints_manifest(prune: &str) -> String {
+        format!(
+            "{}indexerHints:\n  prune: {}\n",
+            data_source_manifest("Thing", 0),
+            prune
+        )
+    }
+
+    let store = test_store::STORE.clone();
+
+    test_store::STORE_RUNTIME.lock().unwrap().block_on(async {
+        // Manifests without hints are still valid
+        let unvalidated = resolve_unvalidated(&data_source_manifest("Thing", 0)).await;
+        let (manifest, _) = unvalidated
+            .validate(store.clone(), &chain_stores(store.clone()))
+            .expect("Manifest without indexer hints is valid");
+        assert_eq!(None, manifest.indexer_hints);
+
+        for (prune, hint) in vec![
+            ("never", PruneHint::Never),
+            ("auto", PruneHint::Auto),
+            ("1000", PruneHint::Blocks(1000)),
+        ] {
+            let unvalidated = resolve_unvalidated(&hints_manifest(prune)).await;
+            let (manifest, _) = unvalidated
+                .validate(store.clone(), &chain_stores(store.clone()))
+                .expect("Manifest with a valid prune hint is valid");
+            let hints = manifest
+                .indexer_hints
+                .expect("The manifest has indexer hints");
+            assert_eq!(Some(prune.to_owned()), hints.prune);
+            assert_eq!(Some(hint), hints.prune_hint());
+        }
+
+        let unvalidated = resolve_unvalidated(&hints_manifest("sometimes")).await;
+        let errors = unvalidated
+            .validate(store.clone(), &chain_stores(store))
+            .expect_err("Manifest with an invalid prune hint is invalid");
+        let errors: Vec<_> = errors
+            .into_iter()
+            .filter(|e| matches!(e, SubgraphManifestValidationError::InvalidPruneHint(_)))
+            .map(|e| e.to_string())
+            .collect();
+        assert_eq!(
+            vec![
+                "`indexerHints.prune` must be `never`, `auto`, or a number of blocks, \
+                  but is `sometimes`"
+                    .to_owned()
+            ],
+            errors
+        );
+    })
+}
+
+#[test]
+fn features_manifest() {
+    fn graft_manifest(features: &str) -> String {
+        format!(
+            "{}graft:\n  base: Qmfeaturebase\n  block: 1\n{}",
+            data_source_manifest("Thing", 0),
+            features
+        )
+    }
+
+    fn grafting_not_declared(warnings: &[SubgraphManifestValidationWarning]) -> bool {
+        warnings.iter().any(|w| {
+            matches!(
+                w,
+                SubgraphManifestValidationWarning::FeatureNotDeclared(SubgraphFeature::Grafting)
+            )
+        })
+    }
+
+    let store = test_store::STORE.clone();
+
+    test_store::STORE_RUNTIME.lock().unwrap().block_on(async {
+        // The base has processed block 1
+        let subgraph = SubgraphDeploymentId::new("Qmfeaturebase").unwrap();
+        test_store::create_test_subgraph(subgraph.as_str(), GQL_SCHEMA);
+        let mut thing = Entity::new();
+        thing.set("id", "datthing");
+        test_store::insert_entities(subgraph.clone(), vec![("Thing", thing)])
+            .expect("Can insert a thing");
+        test_store::transact_entity_operations(
+            &store,
+            subgraph,
+            test_store::BLOCK_ONE.clone(),
+            vec![],
+        )
+        .expect("Can advance to block 1");
+
+        // Grafting without declaring it is allowed, but warned about
+        let unvalidated = resolve_unvalidated(&graft_manifest("")).await;
+        let (_, warnings) = unvalidated
+            .validate(store.clone(), &chain_stores(store.clone()))
+            .expect("Undeclared grafting is not an error");
+        assert!(grafting_not_declared(&warnings));
+
+        // Declaring it makes the warning go away
+        let unvalidated = resolve_unvalidated(&graft_manifest("features:\n  - grafting\n")).await;
+        let (manifest, warnings) = unvalidated
+            .validate(store.clone(), &chain_stores(store.clone()))
+            .expect("Declared grafting is valid");
+        assert_eq!(vec!["grafting".to_owned()], manifest.features);
+        assert!(!grafting_not_declared(&warnings));
+
+        // Unknown features are rejected
+        let unvalidated = resolve_unvalidated(&graft_manifest(
+            "features:\n  - grafting\n  - teleportation\n",
+        ))
+        .await;
+        let errors: Vec<_> = unvalidated
+            .validate(store.clone(), &chain_stores(store))
+            .expect_err("Validation must fail")
+            .into_iter()
+            .map(|e| e.to_string())
+            .collect();
+        assert_eq!(
+            vec!["feature `teleportation` is not known to this Graph Node"],
+            errors
+        );
+    })
+}
+
+#[test]
+fn mapping_entity_not_in_schema_manifest() {
+    let entity_errors = |text: &str| {
+        validation_errors(text, |e| {
+            matches!(
+                e,
+                SubgraphManifestValidationError::MappingEntityNotInSchema { .. }
+            )
+        })
+    };
+
+    // The mapping lists an entity that GQL_SCHEMA does not have
+    let text = data_source_manifest("Thing", 0).replace(
+        "      entities:\n        - Thing\n",
+        "      entities:\n        - Thing\n        - Unknown\n",
+    );
+    assert_eq!(
+        vec![
+            "the mapping of data source `Thing` lists entity `Unknown`, but the schema \
+             has no @entity type with that name"
+        ],
+        entity_errors(&text)
+    );
+
+    // All entities of the mapping are in GQL_SCHEMA
+    assert!(entity_errors(&data_source_manifest("Thing", 0)).is_empty());
 }
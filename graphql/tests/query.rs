@@ -94,6 +94,8 @@ fn insert_test_entities(store: &impl Store, id: SubgraphDeploymentId) {
         data_sources: vec![],
         graft: None,
         templates: vec![],
+        features: vec![],
+        indexer_hints: None,
     };
 
     let ops = SubgraphDeploymentEntity::new(&manifest, false, None, None)
@@ -22,6 +22,8 @@ mock! {
         fn uses_relational_schema(&self, subgraph_id: &SubgraphDeploymentId) -> Result<bool, Error>;
 
         fn network_name(&self, subgraph_id: &SubgraphDeploymentId) -> Result<Option<String>, Error>;
+
+        fn graft_base(&self, subgraph_id: &SubgraphDeploymentId) -> Result<Option<SubgraphDeploymentId>, Error>;
     }
 
     trait ChainStore: Send + Sync + 'static {
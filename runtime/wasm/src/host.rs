@@ -6,7 +6,7 @@ use async_trait::async_trait;
 use ethabi::{LogParam, RawLog};
 use futures::sync::mpsc::Sender;
 use futures03::channel::oneshot::channel;
-use semver::{Version, VersionReq};
+use semver::Version;
 use slog::{o, OwnedKV};
 use strum::AsStaticRef as _;
 use tiny_keccak::keccak256;
@@ -15,7 +15,7 @@ use graph::components::arweave::ArweaveAdapter;
 use graph::components::ethereum::*;
 use graph::components::store::Store;
 use graph::components::three_box::ThreeBoxAdapter;
-use graph::data::subgraph::{Mapping, Source};
+use graph::data::subgraph::{Mapping, Source, MAX_API_VERSION};
 use graph::prelude::{
     RuntimeHost as RuntimeHostTrait, RuntimeHostBuilder as RuntimeHostBuilderTrait, *,
 };
@@ -181,9 +181,10 @@ impl RuntimeHost {
         three_box_adapter: Arc<dyn ThreeBoxAdapter>,
     ) -> Result<Self, Error> {
         let api_version = Version::parse(&config.mapping.api_version)?;
-        if !VersionReq::parse("<= 0.0.4").unwrap().matches(&api_version) {
+        if api_version > *MAX_API_VERSION {
             return Err(format_err!(
-                "This Graph Node only supports mapping API versions <= 0.0.4, but subgraph `{}` uses `{}`",
+                "This Graph Node only supports mapping API versions <= {}, but subgraph `{}` uses `{}`",
+                *MAX_API_VERSION,
                 config.subgraph_id,
                 api_version
             ));
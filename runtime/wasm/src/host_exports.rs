@@ -354,7 +354,7 @@ impl HostExports {
     ///
     /// https://godoc.org/github.com/ethereum/go-ethereum/common/hexutil#hdr-Encoding_Rules
     pub(crate) fn big_int_to_hex(&self, n: BigInt) -> String {
-        if n == 0.into() {
+        if n.is_zero() {
             return "0x0".to_string();
         }
 
@@ -522,7 +522,7 @@ impl HostExports {
         x: BigInt,
         y: BigInt,
     ) -> Result<BigInt, HostExportError<impl ExportError>> {
-        if y == 0.into() {
+        if y.is_zero() {
             return Err(HostExportError(format!(
                 "attempted to divide BigInt `{}` by zero",
                 x
@@ -531,13 +531,23 @@ impl HostExports {
         Ok(x / y)
     }
 
-    pub(crate) fn big_int_mod(&self, x: BigInt, y: BigInt) -> BigInt {
-        x % y
+    pub(crate) fn big_int_mod(
+        &self,
+        x: BigInt,
+        y: BigInt,
+    ) -> Result<BigInt, HostExportError<impl ExportError>> {
+        if y.is_zero() {
+            return Err(HostExportError(format!(
+                "attempted to compute BigInt `{}` modulo zero",
+                x
+            )));
+        }
+        Ok(x % y)
     }
 
     /// Limited to a small exponent to avoid creating huge BigInts.
     pub(crate) fn big_int_pow(&self, x: BigInt, exponent: u8) -> BigInt {
-        x.pow(exponent)
+        x.pow(exponent.into())
     }
 
     pub(crate) fn check_timeout(
@@ -569,13 +579,14 @@ impl HostExports {
         x * y
     }
 
-    /// Maximum precision of 100 decimal digits.
+    /// The quotient is rounded to `BigDecimal::DIVISION_PRECISION` significant
+    /// digits.
     pub(crate) fn big_decimal_divided_by(
         &self,
         x: BigDecimal,
         y: BigDecimal,
     ) -> Result<BigDecimal, HostExportError<impl ExportError>> {
-        if y == 0.into() {
+        if y.is_zero() {
             return Err(HostExportError(format!(
                 "attempted to divide BigDecimal `{}` by zero",
                 x
@@ -781,7 +781,7 @@ impl WasmiModule {
         let result = self
             .ctx
             .host_exports
-            .big_int_mod(self.asc_get(x_ptr), self.asc_get(y_ptr));
+            .big_int_mod(self.asc_get(x_ptr), self.asc_get(y_ptr))?;
         let result_ptr: AscPtr<AscBigInt> = self.asc_new(&result);
         Ok(Some(RuntimeValue::from(result_ptr)))
     }
@@ -10,7 +10,7 @@ use std::str::FromStr;
 use graph::components::store::EntityFilter;
 use graph::data::store::*;
 use graph::prelude::serde_json;
-use graph::prelude::{BigDecimal, BigInt};
+use graph::prelude::{bigdecimal, BigDecimal, BigInt};
 
 use crate::entities::{EntitySource, STRING_PREFIX_SIZE};
 use crate::sql_value::SqlValue;
@@ -124,7 +124,7 @@ impl<QS> IntoFilter<QS> for BigInt {
                 // Using `BigDecimal::new(query_value.0, 0)` results in a
                 // mismatch of `bignum` versions, go through the string
                 // representation to work around that.
-                .bind::<Numeric, _>(BigDecimal::from_str(&self.to_string()).unwrap()),
+                .bind::<Numeric, _>(bigdecimal::BigDecimal::from_str(&self.to_string()).unwrap()),
         ) as FilterExpression<QS>
     }
 }
@@ -136,7 +136,7 @@ impl<QS> IntoFilter<QS> for BigDecimal {
                 .bind::<Text, _>(attribute)
                 .sql("->> 'data')::numeric")
                 .sql(op)
-                .bind::<Numeric, _>(self),
+                .bind::<Numeric, _>(bigdecimal::BigDecimal::from(self)),
         ) as FilterExpression<QS>
     }
 }
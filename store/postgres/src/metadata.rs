@@ -4,8 +4,10 @@ use diesel::prelude::{ExpressionMethods, QueryDsl, RunQueryDsl};
 
 use graph::data::subgraph::schema::SubgraphManifestEntity;
 use graph::prelude::{
-    bigdecimal::ToPrimitive, format_err, web3::types::H256, BigDecimal, EthereumBlockPointer,
-    Schema, StoreError, SubgraphDeploymentId,
+    bigdecimal::{BigDecimal, ToPrimitive},
+    format_err,
+    web3::types::H256,
+    EthereumBlockPointer, Schema, StoreError, SubgraphDeploymentId,
 };
 
 // Diesel tables for some of the metadata
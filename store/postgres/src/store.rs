@@ -1380,6 +1380,14 @@ impl SubgraphDeploymentStore for Store {
     fn network_name(&self, subgraph_id: &SubgraphDeploymentId) -> Result<Option<String>, Error> {
         Ok(self.subgraph_info(subgraph_id)?.network)
     }
+
+    fn graft_base(
+        &self,
+        subgraph_id: &SubgraphDeploymentId,
+    ) -> Result<Option<SubgraphDeploymentId>, Error> {
+        let conn = self.get_conn()?;
+        Ok(metadata::deployment_graft(&conn, subgraph_id)?.map(|(base, _)| base))
+    }
 }
 
 impl ChainStore for Store {
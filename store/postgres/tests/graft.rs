@@ -128,6 +128,8 @@ fn insert_test_data(store: Arc<DieselStore>) {
         data_sources: vec![],
         graft: None,
         templates: vec![],
+        features: vec![],
+        indexer_hints: None,
     };
 
     // Create SubgraphDeploymentEntity
@@ -161,6 +161,8 @@ fn insert_test_data(store: Arc<DieselStore>) {
         data_sources: vec![],
         graft: None,
         templates: vec![],
+        features: vec![],
+        indexer_hints: None,
     };
 
     // Create SubgraphDeploymentEntity
@@ -1609,6 +1611,8 @@ fn entity_changes_are_fired_and_forwarded_to_subscriptions() {
             data_sources: vec![],
             graft: None,
             templates: vec![],
+            features: vec![],
+            indexer_hints: None,
         };
 
         // Create SubgraphDeploymentEntity
@@ -105,6 +105,8 @@ fn create_subgraph(
         data_sources: vec![],
         graft: None,
         templates: vec![],
+        features: vec![],
+        indexer_hints: None,
     };
 
     let ops = SubgraphDeploymentEntity::new(&manifest, false, None, None)